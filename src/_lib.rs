@@ -11,18 +11,20 @@
 //! let nonexistant = "Nonexistant";
 //! assert!(!wsl.is_distribution_registered(nonexistant));
 //! assert!(wsl.get_distribution_configuration(nonexistant).is_err());
+//! assert!(wsl.unregister_distribution(nonexistant).unwrap_err().is_not_found());
 //!
 //! let mut found = 0;
 //! for distro in registry::distribution_names() {
+//!     let distro = distro.unwrap();
 //!     if !wsl.is_distribution_registered(&distro) { continue }
 //!     found += 1;
 //!
 //!     let c = wsl.get_distribution_configuration(&distro).unwrap();
 //!     assert!(c.default_uid == 0 || (1000 ..= 2000).contains(&c.default_uid));
 //!     // 0 == root, 1000+ == regular user
-//!     assert!(c.flags & WSL_DISTRIBUTION_FLAGS::DEFAULT == WSL_DISTRIBUTION_FLAGS::DEFAULT);
-//!     // `c.flags` contains extra, undocumented flags like 0x8
-//!     assert!((1..=2).contains(&c.version)); // WSL version
+//!     assert!(c.flags.interop_enabled() && c.flags.append_nt_path() && c.flags.drive_mounting_enabled());
+//!     // `c.flags` contains extra, undocumented flags like 0x8 - see `c.known_flags()` / `c.unknown_flags()`
+//!     assert!(c.wsl_version().is_some()); // WSL 1 or 2
 //!
 //!     wsl.launch_interactive(&distro, "echo testing 123", true).unwrap();
 //!
@@ -63,10 +65,21 @@
 //!
 //! [wslapi.h]:     https://docs.microsoft.com/en-us/windows/win32/api/wslapi/
 
+mod command;        pub use command::*;
 mod configuration;  pub use configuration::*;
+mod encoding;       pub use encoding::*;
 mod error;          pub use error::*;
 mod flags;          pub use flags::*;
 mod library;        pub use library::*;
+#[cfg(feature = "mock")] mod mock; #[cfg(feature = "mock")] pub use mock::*;
+mod pipe;           pub use pipe::*;
 mod process;        pub use process::*;
+pub mod prelude;
 pub mod registry;
+#[cfg(feature = "serde")] mod serde_impls;
+mod shell;
 mod stdio;          pub use stdio::*;
+pub mod version;
+#[cfg(feature = "async")] mod wait_async;
+mod wsl;            pub use wsl::*;
+mod wsl_exe;
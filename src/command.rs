@@ -0,0 +1,191 @@
+use crate::{Error, Result};
+use crate::error::BoxError;
+use crate::{ExitStatus, Library, Process, Stdio};
+
+use winapi::shared::winerror::E_INVALIDARG;
+
+use std::convert::{TryFrom, TryInto};
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::OsStrExt;
+
+
+
+/// A [std::process::Command]-style builder for [Library::launch], created by [Library::command].
+///
+/// Defaults mirror [Library::launch] with `()` for all stdio:
+/// - `current_dir(false)` - start in the WSL default user's home directory ("~")
+/// - **STDIN**, **STDOUT**, and **STDERR** are [Stdio::null]
+/// - no additional environment variables
+/// - run as the distribution's default user
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// # let wsl = wslapi::Library::new().unwrap();
+/// let status = wsl.command("Ubuntu", "sh")
+///     .stdin("echo testing 123\n")
+///     .stdout(std::fs::File::create("target/command.txt").unwrap())
+///     .status().unwrap();
+/// assert!(status.success());
+/// ```
+///
+/// [Library::launch]:              crate::Library::launch
+/// [Library::command]:             crate::Library::command
+#[must_use = "call spawn() or status() to launch the command"]
+pub struct Command<'l> {
+    library:                        &'l Library,
+    distribution_name:              OsString,
+    command:                        OsString,
+    use_current_working_directory:  bool,
+    env:                            std::result::Result<Vec<(OsString, OsString)>, String>,
+    user:                           Option<OsString>,
+    ensure_registered:              bool,
+    stdin:                          std::result::Result<Stdio, BoxError>,
+    stdout:                         std::result::Result<Stdio, BoxError>,
+    stderr:                         std::result::Result<Stdio, BoxError>,
+}
+
+impl<'l> Command<'l> {
+    pub(crate) fn new(library: &'l Library, distribution_name: &OsStr, command: &OsStr) -> Self {
+        Self {
+            library,
+            distribution_name:              distribution_name.to_owned(),
+            command:                        command.to_owned(),
+            use_current_working_directory:  false,
+            env:                            Ok(Vec::new()),
+            user:                           None,
+            ensure_registered:              false,
+            stdin:                          Ok(Stdio::null()),
+            stdout:                         Ok(Stdio::null()),
+            stderr:                         Ok(Stdio::null()),
+        }
+    }
+
+    /// Governs whether or not the launched process should inherit the calling process's working directory.
+    /// If `false` (the default), the process is started in the WSL default user's home directory ("~").
+    pub fn current_dir(mut self, use_current_working_directory: bool) -> Self {
+        self.use_current_working_directory = use_current_working_directory;
+        self
+    }
+
+    /// Set an environment variable for the launched command.
+    ///
+    /// `wslapi.dll` has no parameter for this, so the command is prefixed with `export 'KEY=VALUE' && `, quoted for a
+    /// POSIX shell - spaces, quotes, `$`, etc. in `value` are passed through literally.  `key` must be a valid shell
+    /// identifier (`[A-Za-z_][A-Za-z0-9_]*`, e.g. `RUST_LOG`.)  Invalid keys, and values containing `'\0'`, are reported
+    /// as errors by [spawn](#method.spawn) / [status](#method.status).
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        if let Ok(env) = self.env.as_mut() {
+            let wkey = key.encode_wide().collect::<Vec<_>>();
+            if wkey.is_empty() {
+                self.env = Err(String::from("environment variable name was empty"));
+            } else if !is_shell_identifier(&wkey) {
+                self.env = Err(format!("environment variable name {:?} isn't a valid shell identifier", key));
+            } else if value.encode_wide().any(|ch| ch == 0) {
+                self.env = Err(format!("environment variable {:?} contained '\\0'", key));
+            } else {
+                env.push((key.to_owned(), value.to_owned()));
+            }
+        }
+        self
+    }
+
+    /// Set multiple environment variables for the launched command.  See [env](#method.env).
+    pub fn envs<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self {
+        for (key, value) in vars { self = self.env(key, value); }
+        self
+    }
+
+    /// Run the command as a different Linux user, without changing the distribution's default user.
+    ///
+    /// `wslapi.dll` has no parameter for this, so the command is wrapped with `runuser -u <user> -- /bin/sh -c '...'` if the
+    /// default user is root, or `sudo -n -u <user> -- ...` otherwise.  This depends on the distribution having the
+    /// relevant tooling (`runuser` from util-linux, or `sudo`), and for non-root default users, on passwordless sudo -
+    /// `-n` fails instead of prompting.  `name_or_uid` is a user name (e.g. `"postgres"`) or numeric UID (e.g. `"1000"`.)
+    pub fn user(mut self, name_or_uid: impl AsRef<OsStr>) -> Self {
+        self.user = Some(name_or_uid.as_ref().to_owned());
+        self
+    }
+
+    /// Check that the distribution is registered (via [Library::is_distribution_registered]) before launching, failing
+    /// with a clear "not registered" error ([Error::is_not_found]) instead of whatever [WslLaunch] would return.
+    ///
+    /// Opt-in, since it costs an extra call into `wslapi.dll` per launch.
+    ///
+    /// [Library::is_distribution_registered]:  crate::Library::is_distribution_registered
+    /// [WslLaunch]:                            https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn ensure_registered(mut self) -> Self {
+        self.ensure_registered = true;
+        self
+    }
+
+    /// Handle to use for **STDIN**.  Conversion errors are reported by [spawn](#method.spawn) / [status](#method.status).
+    pub fn stdin<I>(mut self, stdin: I) -> Self where I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>> {
+        self.stdin = stdin.try_into().map_err(Into::into);
+        self
+    }
+
+    /// Handle to use for **STDOUT**.  Conversion errors are reported by [spawn](#method.spawn) / [status](#method.status).
+    pub fn stdout<O>(mut self, stdout: O) -> Self where O : TryInto<Stdio>, O::Error : Into<Box<dyn std::error::Error + Send + Sync>> {
+        self.stdout = stdout.try_into().map_err(Into::into);
+        self
+    }
+
+    /// Handle to use for **STDERR**.  Conversion errors are reported by [spawn](#method.spawn) / [status](#method.status).
+    pub fn stderr<E>(mut self, stderr: E) -> Self where E : TryInto<Stdio>, E::Error : Into<Box<dyn std::error::Error + Send + Sync>> {
+        self.stderr = stderr.try_into().map_err(Into::into);
+        self
+    }
+
+    /// Launch the command via [Library::launch].
+    ///
+    /// [Library::launch]:          crate::Library::launch
+    pub fn spawn(self) -> Result<Process> {
+        let Self { library, distribution_name, command, use_current_working_directory, env, user, ensure_registered, stdin, stdout, stderr } = self;
+        if ensure_registered && !library.is_distribution_registered(&distribution_name) {
+            return Err(Error::new(crate::error::WSL_E_DISTRO_NOT_FOUND, format!("launch({:?}, {:?}, {}, ...) failed: distribution {:?} is not registered", distribution_name, command, use_current_working_directory, distribution_name)).with_command(&command));
+        }
+        let convert_err = |stream, err: BoxError| Error::new(E_INVALIDARG, format!("launch({:?}, {:?}, {}, ...) failed: failed to convert {}: {}", distribution_name, command, use_current_working_directory, stream, err)).with_command(&command).with_source(err);
+        let env    = env.map_err(|err| Error::new(E_INVALIDARG, format!("launch({:?}, {:?}, {}, ...) failed: {}", distribution_name, command, use_current_working_directory, err)).with_command(&command))?;
+        let command = crate::shell::export(&env, &command);
+        let command = match user { Some(user) => crate::shell::as_user(&user, &command), None => command };
+        let stdin  = stdin .map_err(|err| convert_err("stdin",  err))?;
+        let stdout = stdout.map_err(|err| convert_err("stdout", err))?;
+        let stderr = stderr.map_err(|err| convert_err("stderr", err))?;
+        library.launch(&distribution_name, &command, use_current_working_directory, stdin, stdout, stderr)
+    }
+
+    /// Launch the command via [Library::launch], and wait for it to exit.
+    ///
+    /// [Library::launch]:          crate::Library::launch
+    pub fn status(self) -> Result<ExitStatus> {
+        let desc = format!("{:?} in {:?}", self.command, self.distribution_name);
+        let command = self.command.clone();
+        self.spawn()?.wait().map_err(|err| Error::new(crate::error::io2hr(&err), format!("waiting for {} failed: {}", desc, err)).with_command(&command).with_source(err))
+    }
+}
+
+fn is_shell_identifier(name: &[u16]) -> bool {
+    let is = |ch: u16, f: fn(&u8) -> bool| u8::try_from(ch).map_or(false, |ch| f(&ch));
+    match name.split_first() {
+        Some((&first, rest))    => (first == u16::from(b'_') || is(first, u8::is_ascii_alphabetic)) && rest.iter().all(|&ch| ch == u16::from(b'_') || is(ch, u8::is_ascii_alphanumeric)),
+        None                    => false,
+    }
+}
+
+#[test] fn shell_identifiers() {
+    let is = |name: &str| is_shell_identifier(&name.encode_utf16().collect::<Vec<_>>());
+    assert!( is("RUST_LOG"));
+    assert!( is("_"));
+    assert!( is("_a1"));
+    assert!( is("x"));
+    assert!(!is(""));
+    assert!(!is("1X"));
+    assert!(!is("A=B"));
+    assert!(!is("A\0"));
+    assert!(!is("MY-VAR"));
+    assert!(!is("MY VAR"));
+    assert!(!is("$(rm -rf ~)"));
+    assert!(!is("caf\u{e9}"));
+}
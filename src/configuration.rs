@@ -1,10 +1,15 @@
-use crate::WSL_DISTRIBUTION_FLAGS;
+use crate::{Library, Result, WSL_DISTRIBUTION_FLAGS};
 
 use winapi::shared::ntdef::{PSTR, ULONG};
-use winapi::um::combaseapi::CoTaskMemFree;
+use winapi::um::combaseapi::{CoTaskMemAlloc, CoTaskMemFree};
 
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::{self, Debug, Formatter};
+use std::iter::FromIterator;
 use std::ops::Drop;
 use std::ptr::null_mut;
+use std::str::Utf8Error;
 
 // winapi uses `CHAR` = `c_char` = `i8`, but `u8` is way more rusty considering
 // the env vars in question might actually be UTF8, so I expose `&[u8]` instead
@@ -13,7 +18,8 @@ use std::ptr::null_mut;
 
 
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The structified result of [WslGetDistributionConfiguration]
 /// 
 /// [WslGetDistributionConfiguration]:      https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslgetdistributionconfiguration
@@ -31,7 +37,119 @@ pub struct Configuration {
     pub default_environment_variables:  EnvironmentVariables,
 }
 
+impl Configuration {
+    /// [version](#structfield.version) as a [WslVersion], or `None` if it's neither `1` nor `2`.
+    pub fn wsl_version(&self) -> Option<WslVersion> { WslVersion::from_raw(self.version) }
 
+    /// [flags](#structfield.flags), masked to only the documented [WSL_DISTRIBUTION_FLAGS::VALID] bits.
+    ///
+    /// Suitable for feeding back into [Library::configure_distribution] without also re-applying undocumented flags.
+    ///
+    /// [Library::configure_distribution]:  crate::Library::configure_distribution
+    pub fn known_flags(&self) -> WSL_DISTRIBUTION_FLAGS { self.flags & WSL_DISTRIBUTION_FLAGS::VALID }
+
+    /// Any undocumented bits of [flags](#structfield.flags) (e.g. `0x8`) outside of [WSL_DISTRIBUTION_FLAGS::VALID], for diagnostics.
+    pub fn unknown_flags(&self) -> WSL_DISTRIBUTION_FLAGS { self.flags - WSL_DISTRIBUTION_FLAGS::VALID }
+}
+
+
+/// Prints the number of [default_environment_variables](#structfield.default_environment_variables), rather than their
+/// contents (which may be lengthy, or sensitive.)  [Debug] them directly if you want to see them.
+impl Debug for Configuration {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Configuration")
+            .field("version",                       &self.version)
+            .field("default_uid",                   &self.default_uid)
+            .field("flags",                         &self.flags)
+            .field("default_environment_variables", &format_args!("[{} variables]", self.default_environment_variables.len()))
+            .finish()
+    }
+}
+
+
+
+/// The version of WSL a distribution is configured for (see [Configuration::wsl_version].)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WslVersion {
+    /// WSL 1 - Linux syscalls are translated by the Windows kernel
+    V1,
+
+    /// WSL 2 - a real Linux kernel, running in a lightweight utility VM
+    V2,
+}
+
+impl WslVersion {
+    /// Convert a raw [Configuration::version] (`1` or `2`) into a [WslVersion].
+    pub fn from_raw(version: ULONG) -> Option<Self> {
+        match version {
+            1 => Some(WslVersion::V1),
+            2 => Some(WslVersion::V2),
+            _ => None,
+        }
+    }
+
+    /// Convert back into a raw [Configuration::version] (`1` or `2`.)
+    pub fn to_raw(self) -> ULONG {
+        match self {
+            WslVersion::V1 => 1,
+            WslVersion::V2 => 2,
+        }
+    }
+}
+
+
+
+/// A pending modification of a distribution's configuration, created by [Library::configure].
+///
+/// Starts from the distribution's current configuration, so only the settings you explicitly change are modified
+/// (including undocumented flags.)
+///
+/// [Library::configure]:   crate::Library::configure
+#[must_use = "call apply() to modify the distribution's configuration"]
+pub struct ConfigurationBuilder<'l> {
+    library:            &'l Library,
+    distribution_name:  OsString,
+    default_uid:        ULONG,
+    flags:              WSL_DISTRIBUTION_FLAGS,
+}
+
+impl<'l> ConfigurationBuilder<'l> {
+    pub(crate) fn new(library: &'l Library, distribution_name: OsString, current: &Configuration) -> Self {
+        Self { library, distribution_name, default_uid: current.default_uid, flags: current.flags }
+    }
+
+    /// The Linux user ID to use when launching new WSL sessions for this distribution.
+    pub fn default_uid(mut self, default_uid: ULONG) -> Self { self.default_uid = default_uid; self }
+
+    /// Set or clear [WSL_DISTRIBUTION_FLAGS::ENABLE_INTEROP]
+    pub fn enable_interop(self, enable: bool) -> Self { self.flag(WSL_DISTRIBUTION_FLAGS::ENABLE_INTEROP, enable) }
+
+    /// Set or clear [WSL_DISTRIBUTION_FLAGS::APPEND_NT_PATH]
+    pub fn append_nt_path(self, enable: bool) -> Self { self.flag(WSL_DISTRIBUTION_FLAGS::APPEND_NT_PATH, enable) }
+
+    /// Set or clear [WSL_DISTRIBUTION_FLAGS::ENABLE_DRIVE_MOUNTING]
+    pub fn enable_drive_mounting(self, enable: bool) -> Self { self.flag(WSL_DISTRIBUTION_FLAGS::ENABLE_DRIVE_MOUNTING, enable) }
+
+    /// Apply the modified configuration via [Library::configure_distribution].
+    ///
+    /// Flags the distribution already had - including undocumented ones like [WSL_DISTRIBUTION_FLAGS::RESERVED_0x8] -
+    /// are passed through unchanged rather than rejected.
+    ///
+    /// [Library::configure_distribution]:  crate::Library::configure_distribution
+    pub fn apply(self) -> Result<()> {
+        self.library.configure_distribution(&self.distribution_name, self.default_uid, self.flags)
+    }
+
+    fn flag(mut self, flag: WSL_DISTRIBUTION_FLAGS, enable: bool) -> Self {
+        if enable { self.flags |= flag } else { self.flags -= flag }
+        self
+    }
+}
+
+
+
+type Utf8KeyValue<'e> = (std::result::Result<&'e str, Utf8Error>, std::result::Result<&'e str, Utf8Error>);
 
 /// The environment variables of [WslGetDistributionConfiguration].
 /// 
@@ -42,41 +160,152 @@ pub struct EnvironmentVariables {
 }
 
 impl EnvironmentVariables {
+    /// The maximum length, in bytes, of a single `KEY=VALUE` string that [get](Self::get) will scan for a `'\0'` terminator.
+    ///
+    /// Linux itself limits individual environment strings to 128 KiB (`MAX_ARG_STRLEN`), so this is quite generous.
+    pub const MAX_VAR_LEN : usize = 4 * 1024 * 1024;
+
     /// Create an empty/null array of environment variables
     pub fn new() -> Self { Self { array: null_mut(), count: 0 } }
 
+    /// Create an array of `KEY=VALUE` environment variables, allocated with [CoTaskMemAlloc] just like
+    /// [WslGetDistributionConfiguration] would.  Useful for synthesizing a [Configuration] in tests.
+    ///
+    /// Also available via [FromIterator], e.g. `vec![("LANG", "en_US.UTF-8")].into_iter().collect()`.
+    ///
+    /// ### Panics
+    ///
+    /// - If a key contains `'='` or `'\0'`, or a value contains `'\0'` - these couldn't be read back by [get](#method.get)
+    /// - If [CoTaskMemAlloc] fails
+    ///
+    /// [CoTaskMemAlloc]:                       https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-cotaskmemalloc
+    /// [WslGetDistributionConfiguration]:      https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslgetdistributionconfiguration
+    pub fn from_pairs<K: AsRef<[u8]>, V: AsRef<[u8]>>(pairs: impl IntoIterator<Item = (K, V)>) -> Self {
+        let pairs = pairs.into_iter().collect::<Vec<_>>();
+        for (k, v) in pairs.iter() {
+            let (k, v) = (k.as_ref(), v.as_ref());
+            assert!(!k.contains(&b'=') && !k.contains(&0), "environment variable name {:?} contained '=' or '\\0'", String::from_utf8_lossy(k));
+            assert!(!v.contains(&0), "environment variable {:?} contained '\\0'", String::from_utf8_lossy(k));
+        }
+
+        let mut vars = Self::new();
+        if pairs.is_empty() { return vars }
+
+        vars.array = unsafe { CoTaskMemAlloc(pairs.len() * std::mem::size_of::<PSTR>()) }.cast();
+        assert!(!vars.array.is_null(), "CoTaskMemAlloc failed");
+        for (k, v) in pairs.iter() {
+            let (k, v) = (k.as_ref(), v.as_ref());
+            let len = k.len() + 1 + v.len() + 1; // "k=v\0"
+            let st : *mut u8 = unsafe { CoTaskMemAlloc(len) }.cast();
+            assert!(!st.is_null(), "CoTaskMemAlloc failed");
+            let kv = unsafe { std::slice::from_raw_parts_mut(st, len) };
+            kv[.. k.len()].copy_from_slice(k);
+            kv[k.len()] = b'=';
+            kv[k.len()+1 .. len-1].copy_from_slice(v);
+            kv[len-1] = b'\0';
+            unsafe { *vars.array.add(vars.count) = st.cast() };
+            vars.count += 1; // only count fully initialized entries, in case a later allocation panics
+        }
+        vars
+    }
+
     /// Get the number of environment variables
     pub fn len(&self) -> usize { self.count }
 
     /// Get the key/value pair at `index`
+    ///
+    /// Returns `None` if `index` is out of bounds, or if the entry is `NULL` or isn't `'\0'`-terminated within
+    /// [MAX_VAR_LEN](Self::MAX_VAR_LEN) bytes.  Note that the bound only stops the scan from running on forever: if an
+    /// entry really is unterminated, bytes past the end of its allocation will still have been read.
     pub fn get(&self, index: usize) -> Option<(&[u8], &[u8])> {
-        if index >= self.count {
-            None
-        } else {
-            let st = unsafe { *self.array.add(index) };
-
-            for i in 0.. {
-                match unsafe { *st.add(i) } as _ {
-                    b'\0' => return Some((unsafe { std::slice::from_raw_parts(st.cast(), i) }, &[])),
-                    b'=' => for k in i.. {
-                        if unsafe { *st.add(k) } != 0 { continue }
-
-                        let all = unsafe { std::slice::from_raw_parts(st.cast(), k) };
-                        let (k, v) = all.split_at(i);
-                        return Some((k, &v[1..]));
-                    },
-                    _ => {},
-                }
-            }
+        if index >= self.count { return None }
+        let st = unsafe { *self.array.add(index) };
+        if st.is_null() { return None }
 
-            None // unreachable
+        let mut eq = None;
+        for i in 0 .. Self::MAX_VAR_LEN {
+            match unsafe { *st.add(i) } as _ {
+                b'\0' => {
+                    let all = unsafe { std::slice::from_raw_parts(st.cast(), i) };
+                    return Some(match eq {
+                        None    => (all, &[]),
+                        Some(e) => (&all[..e], &all[e+1..]),
+                    });
+                },
+                b'=' if eq.is_none() => eq = Some(i),
+                _ => {},
+            }
         }
+
+        None // unterminated, or absurdly long
     }
 
     /// Iterate over the key/value pairs
-    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+    ///
+    /// Entries that [get](Self::get) would return `None` for are yielded as `(b"", b"")`, so the iterator always
+    /// yields exactly [len](Self::len) pairs.
+    pub fn iter(&self) -> EnvironmentVariablesIter<'_> {
         EnvironmentVariablesIter { vars: self, index: 0 }
     }
+
+    /// Get the value of the first variable whose key is exactly `key` (case-sensitive), e.g. `get_by_key(b"PATH")`.
+    ///
+    /// Note that if `key` occurs multiple times, this returns the *first* occurrence, unlike [to_hashmap](Self::to_hashmap).
+    pub fn get_by_key(&self, key: &[u8]) -> Option<&[u8]> {
+        self.iter().find(|&(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Returns `true` if any variable's key is exactly `key` (case-sensitive.)
+    pub fn contains_key(&self, key: &[u8]) -> bool { self.get_by_key(key).is_some() }
+
+    /// Like [get](Self::get), but `NULL` or unterminated entries are returned as `(b"", b"")`
+    fn get_or_empty(&self, index: usize) -> Option<(&[u8], &[u8])> {
+        if index >= self.count { return None }
+        Some(self.get(index).unwrap_or((&[], &[])))
+    }
+
+    /// Get the key/value pair at `index`, interpreted as UTF8
+    pub fn get_str(&self, index: usize) -> Option<Utf8KeyValue<'_>> {
+        let (k, v) = self.get(index)?;
+        Some((std::str::from_utf8(k), std::str::from_utf8(v)))
+    }
+
+    /// Iterate over the key/value pairs, interpreted as UTF8
+    pub fn iter_str(&self) -> impl Iterator<Item = Utf8KeyValue<'_>> {
+        self.iter().map(|(k, v)| (std::str::from_utf8(k), std::str::from_utf8(v)))
+    }
+
+    /// Copy the key/value pairs into a new [HashMap], which may outlive `self`.
+    ///
+    /// If a key occurs multiple times, the last occurrence wins.
+    pub fn to_hashmap(&self) -> HashMap<Vec<u8>, Vec<u8>> {
+        self.iter().map(|(k, v)| (k.to_vec(), v.to_vec())).collect()
+    }
+
+    /// Copy the key/value pairs into a new [HashMap], which may outlive `self`, lossily converting them to UTF8.
+    ///
+    /// If a key occurs multiple times, the last occurrence wins.
+    pub fn to_string_hashmap(&self) -> HashMap<String, String> {
+        self.iter().map(|(k, v)| (String::from_utf8_lossy(k).into_owned(), String::from_utf8_lossy(v).into_owned())).collect()
+    }
+}
+
+/// Deep copies the key/value pairs into a new [CoTaskMemAlloc]ed array.
+///
+/// [CoTaskMemAlloc]:   https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-cotaskmemalloc
+impl Clone for EnvironmentVariables {
+    fn clone(&self) -> Self { Self::from_pairs(self.iter()) }
+}
+
+/// Prints `["KEY=VALUE", ...]`, lossily converted to UTF8.
+impl Debug for EnvironmentVariables {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.iter().map(|(k, v)| format!("{}={}", String::from_utf8_lossy(k), String::from_utf8_lossy(v)))).finish()
+    }
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> FromIterator<(K, V)> for EnvironmentVariables {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self { Self::from_pairs(iter) }
 }
 
 impl Default for EnvironmentVariables {
@@ -108,6 +337,15 @@ impl<'e> IntoIterator for &'e EnvironmentVariables {
     }
 }
 
+/// Copies each key/value pair into owned `Vec<u8>`s, freeing the underlying array when the iterator is dropped.
+impl IntoIterator for EnvironmentVariables {
+    type Item = (Vec<u8>, Vec<u8>);
+    type IntoIter = EnvironmentVariablesIntoIter;
+    fn into_iter(self) -> Self::IntoIter {
+        EnvironmentVariablesIntoIter { vars: self, index: 0 }
+    }
+}
+
 
 
 /// Iterator over &[EnvironmentVariables]
@@ -119,8 +357,112 @@ pub struct EnvironmentVariablesIter<'e> {
 impl<'e> Iterator for EnvironmentVariablesIter<'e> {
     type Item = (&'e [u8], &'e [u8]);
     fn next(&mut self) -> Option<Self::Item> {
-        let kv = self.vars.get(self.index)?;
+        let kv = self.vars.get_or_empty(self.index)?;
         self.index += 1;
         Some(kv)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vars.count.saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'e> ExactSizeIterator for EnvironmentVariablesIter<'e> {}
+
+/// Owning iterator over [EnvironmentVariables]
+pub struct EnvironmentVariablesIntoIter {
+    vars:   EnvironmentVariables,
+    index:  usize,
+}
+
+impl Iterator for EnvironmentVariablesIntoIter {
+    type Item = (Vec<u8>, Vec<u8>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (k, v) = self.vars.get_or_empty(self.index)?;
+        self.index += 1;
+        Some((k.to_vec(), v.to_vec()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vars.count.saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for EnvironmentVariablesIntoIter {}
+
+#[test] fn from_pairs() {
+    let empty : &[(&[u8], &[u8])] = &[];
+    assert_eq!(EnvironmentVariables::from_pairs(empty.iter().copied()).len(), 0);
+
+    let pairs : &[(&[u8], &[u8])] = &[(b"HOSTTYPE", b"x86_64"), (b"LANG", b"en_US.UTF-8"), (b"EMPTY", b"")];
+    let vars = EnvironmentVariables::from_pairs(pairs.iter().copied());
+    assert_eq!(vars.len(), 3);
+    assert_eq!(vars.iter().collect::<Vec<_>>(), pairs);
+
+    let vars : EnvironmentVariables = vec![("LANG", "C"), ("TERM", "xterm")].into_iter().collect();
+    assert_eq!(vars.get(1), Some((&b"TERM"[..], &b"xterm"[..])));
+
+    let vars : EnvironmentVariables = vec![("PATH", "/bin"), ("EMPTY", ""), ("PATH", "/usr/bin")].into_iter().collect();
+    assert_eq!(vars.get_by_key(b"PATH"),    Some(&b"/bin"[..]));
+    assert_eq!(vars.get_by_key(b"EMPTY"),   Some(&b""[..]));
+    assert_eq!(vars.get_by_key(b"path"),    None);
+    assert!( vars.contains_key(b"EMPTY"));
+    assert!(!vars.contains_key(b"PAT"));
+}
+
+#[test] fn wsl_version() {
+    assert_eq!(WslVersion::from_raw(0), None);
+    assert_eq!(WslVersion::from_raw(1), Some(WslVersion::V1));
+    assert_eq!(WslVersion::from_raw(2), Some(WslVersion::V2));
+    assert_eq!(WslVersion::from_raw(3), None);
+    assert_eq!(WslVersion::V2.to_raw(), 2);
+    assert_eq!(Configuration { version: 1, ..Default::default() }.wsl_version(), Some(WslVersion::V1));
+}
+
+#[test] fn get_unterminated() {
+    let mut unterminated = vec![b'A'; EnvironmentVariables::MAX_VAR_LEN];
+    let mut array = [unterminated.as_mut_ptr().cast(), null_mut()];
+    let vars = EnvironmentVariables { array: array.as_mut_ptr(), count: 2 };
+    assert_eq!(vars.get(0), None);
+    assert_eq!(vars.get(1), None);
+    assert_eq!(vars.get(2), None);
+    std::mem::forget(vars); // not CoTaskMemAlloc()ed
+}
+
+#[test] fn iter_null_entry() {
+    let vars : EnvironmentVariables = vec![("A", "1"), ("B", "2"), ("C", "3")].into_iter().collect();
+    unsafe { CoTaskMemFree((*vars.array.add(1)).cast()) };
+    unsafe { *vars.array.add(1) = null_mut() };
+
+    assert_eq!(vars.get(1), None);
+    let expected : &[(&[u8], &[u8])] = &[(b"A", b"1"), (b"", b""), (b"C", b"3")];
+    assert_eq!(vars.iter().len(), 3);
+    assert_eq!(vars.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(vars.clone().iter().collect::<Vec<_>>(), expected);
+    assert_eq!(vars.get_by_key(b"C"), Some(&b"3"[..]));
+    assert_eq!(vars.into_iter().map(|(k, _)| k).collect::<Vec<_>>(), [b"A".to_vec(), Vec::new(), b"C".to_vec()]);
+}
+
+#[test] fn debug() {
+    let config = Configuration { version: 2, default_uid: 1000, flags: WSL_DISTRIBUTION_FLAGS::DEFAULT, default_environment_variables: vec![("LANG", "C"), ("EMPTY", "")].into_iter().collect() };
+    assert_eq!(format!("{:?}", config), "Configuration { version: 2, default_uid: 1000, flags: WSL_DISTRIBUTION_FLAGS::DEFAULT, default_environment_variables: [2 variables] }");
+    assert_eq!(format!("{:?}", config.default_environment_variables), r#"["LANG=C", "EMPTY="]"#);
+}
+
+#[test] fn into_iter_owned() {
+    let config = Configuration { default_environment_variables: vec![("LANG", "C"), ("TERM", "xterm")].into_iter().collect(), ..Configuration::default() };
+    let mut owned = Vec::new();
+    for (k, v) in config.default_environment_variables { owned.push((k, v)); }
+    assert_eq!(owned, [(b"LANG".to_vec(), b"C".to_vec()), (b"TERM".to_vec(), b"xterm".to_vec())]);
+    assert_eq!(EnvironmentVariables::new().into_iter().len(), 0);
+}
+
+#[test] fn clone() {
+    let config = Configuration { version: 2, default_environment_variables: vec![("LANG", "C")].into_iter().collect(), ..Default::default() };
+    let copy = config.clone();
+    drop(config);
+    assert_eq!(copy.version, 2);
+    assert_eq!(copy.default_environment_variables.get(0), Some((&b"LANG"[..], &b"C"[..])));
 }
@@ -0,0 +1,74 @@
+use winapi::shared::minwindef::DWORD;
+use winapi::um::stringapiset::MultiByteToWideChar;
+use winapi::um::winnls::CP_ACP;
+
+use std::convert::{TryFrom, TryInto};
+
+
+
+/// The expected encoding of bytes captured from a WSL process (e.g. [Output::stdout]), for [decode](#method.decode)ing.
+///
+/// Captured bytes are always *raw*: exactly what the Linux process wrote, unaffected by the Windows console code page
+/// (`chcp`), so don't rely on `print!`-ing them to a console to reinterpret them.  Most Linux tools write UTF-8, but
+/// some Windows interop (e.g. running `cmd.exe` from within WSL) writes in the system locale's ANSI code page instead.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// # use wslapi::*;
+/// # let wsl = Library::new().unwrap();
+/// let output = wsl.output("Ubuntu", "cmd.exe /c echo %USERNAME%", false, ()).unwrap();
+/// let username = output.stdout_decoded(Encoding::SystemLocale);
+/// ```
+///
+/// [Output::stdout]:               crate::Output::stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8, as written by most Linux tools.  The default.
+    #[default] Utf8,
+
+    /// The Windows system locale's ANSI code page (`CP_ACP`, e.g. Windows-1252 or Shift-JIS), as written by many Windows
+    /// tools when invoked via interop.
+    SystemLocale,
+}
+
+impl Encoding {
+    /// Decode `bytes`, replacing invalid sequences with `U+FFFD`.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8          => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::SystemLocale  => decode_code_page(CP_ACP, bytes),
+        }
+    }
+}
+
+fn decode_code_page(code_page: DWORD, mut bytes: &[u8]) -> String {
+    // MultiByteToWideChar takes `int` lengths, so decode in chunks, split after '\n' (never a DBCS trail byte) when possible.
+    const MAX : usize = i32::MAX as usize;
+    let mut wide = Vec::new();
+    while !bytes.is_empty() {
+        let len = if bytes.len() <= MAX { bytes.len() } else { bytes[..MAX].iter().rposition(|b| *b == b'\n').map_or(MAX, |nl| nl + 1) };
+        let (chunk, rest) = bytes.split_at(len);
+        bytes = rest;
+
+        // ANSI and DBCS code pages decode each byte into at most one UTF-16 code unit
+        let start = wide.len();
+        wide.resize(start + len, 0);
+        let chars = unsafe { MultiByteToWideChar(code_page, 0, chunk.as_ptr().cast(), len.try_into().unwrap(), wide[start..].as_mut_ptr(), len.try_into().unwrap()) };
+        if chars == 0 { // shouldn't happen (even for a UTF-8 system code page), but fall back on lossy UTF-8 if it does
+            wide.truncate(start);
+            wide.extend(String::from_utf8_lossy(chunk).encode_utf16());
+        } else {
+            wide.truncate(start + usize::try_from(chars).unwrap());
+        }
+    }
+    String::from_utf16_lossy(&wide)
+}
+
+#[test] fn decode() {
+    assert_eq!(Encoding::default(), Encoding::Utf8);
+    assert_eq!(Encoding::Utf8.decode(b"caf\xC3\xA9\n"), "caf\u{e9}\n");
+    assert_eq!(Encoding::Utf8.decode(b"caf\xE9\n"),     "caf\u{FFFD}\n");
+    assert_eq!(Encoding::SystemLocale.decode(b""),      "");
+    assert_eq!(Encoding::SystemLocale.decode(b"ASCII is the same everywhere\r\n"), "ASCII is the same everywhere\r\n");
+}
@@ -1,6 +1,8 @@
 use winapi::shared::winerror::*;
 
+use std::ffi::{OsStr, OsString};
 use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::io;
 
 
@@ -9,22 +11,174 @@ use std::io;
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// A crate error.  Convertable to [std::io::Error], Box<dyn [std::error::Error]>
+#[non_exhaustive]
 pub struct Error {
     pub(crate) hresult: HRESULT,
     pub(crate) message: String,
+    pub(crate) command: Option<OsString>,
+    pub(crate) source:  Option<BoxError>,
 }
 
-impl std::error::Error for Error {}
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A rough classification of an [Error], as returned by [Error::kind].
+///
+/// There's deliberately no `InteropDisabled` kind: `wslapi.dll` has no HRESULT for it.  With interop disabled, [WslLaunch]
+/// still succeeds - it's the Windows executable *within* the WSL process that fails to start (typically reported by the
+/// Linux shell as "Exec format error", with exit status 126), so no [Error] is ever returned.  Check
+/// <code>[Library::get_distribution_configuration]\(...\)?.flags.[interop_enabled]\(\)</code> up front instead.
+///
+/// [WslLaunch]:                                https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+/// [Library::get_distribution_configuration]:  crate::Library::get_distribution_configuration
+/// [interop_enabled]:                          crate::WSL_DISTRIBUTION_FLAGS::interop_enabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The distribution isn't registered (`WSL_E_DISTRO_NOT_FOUND`, `HRESULT_FROM_WIN32(ERROR_NOT_FOUND)`)
+    DistributionNotFound,
+
+    /// The distribution (or a file) already exists (`HRESULT_FROM_WIN32(ERROR_ALREADY_EXISTS)`, `HRESULT_FROM_WIN32(ERROR_FILE_EXISTS)`)
+    AlreadyExists,
+
+    /// A name was rejected as invalid (`HRESULT_FROM_WIN32(ERROR_INVALID_NAME)`)
+    InvalidName,
+
+    /// An argument was invalid, such as a name containing `'\0'` (`E_INVALIDARG`)
+    InvalidArgument,
+
+    /// Any other error
+    Other(HRESULT),
+}
+
+/// `MAKE_HRESULT(SEVERITY_ERROR, FACILITY_ITF, WSL_E_BASE + 2)`, returned by newer versions of WSL for unknown distributions
+pub(crate) const WSL_E_DISTRO_NOT_FOUND : HRESULT = 0x80040302_u32 as HRESULT;
+
+impl Error {
+    /// Create a new error from an [HRESULT] and a human readable message.
+    ///
+    /// Useful for adapter crates (or test doubles) that want to report errors consistent with this crate's own.
+    ///
+    /// ```rust
+    /// let err = wslapi::Error::new(winapi::shared::winerror::E_INVALIDARG, "my_launch(...) failed: command was empty");
+    /// assert_eq!(err.kind(), wslapi::ErrorKind::InvalidArgument);
+    /// assert_eq!(err.to_string(), "my_launch(...) failed: command was empty");
+    /// ```
+    ///
+    /// [HRESULT]:      https://docs.microsoft.com/en-us/windows/win32/com/structure-of-com-error-codes
+    pub fn new(hresult: HRESULT, message: impl Into<String>) -> Self {
+        Self { hresult, message: message.into(), command: None, source: None }
+    }
+
+    pub(crate) fn with_source(mut self, source: impl Into<BoxError>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub(crate) fn with_command(mut self, command: &OsStr) -> Self {
+        self.command = Some(command.to_owned());
+        self
+    }
+
+    /// The command line that failed to launch, for errors from [Library::launch] and friends.
+    ///
+    /// For [Library::launch_args] etc. this is the final, quoted command line that was handed to WSL.
+    ///
+    /// [Library::launch]:          crate::Library::launch
+    /// [Library::launch_args]:     crate::Library::launch_args
+    pub fn command(&self) -> Option<&OsStr> { self.command.as_deref() }
+
+    /// The underlying [HRESULT] (e.g. `E_INVALIDARG` = `0x80070057`) of this error.
+    ///
+    /// [HRESULT]:      https://docs.microsoft.com/en-us/windows/win32/com/structure-of-com-error-codes
+    pub fn hresult(&self) -> HRESULT { self.hresult }
+
+    /// Returns `true` if this error's [HRESULT](Self::hresult) is `hresult`.
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// let err = wsl.unregister_distribution("Ubu\0ntu").unwrap_err();
+    /// assert!(err.matches(winapi::shared::winerror::E_INVALIDARG));
+    /// ```
+    pub fn matches(&self, hresult: HRESULT) -> bool { self.hresult == hresult }
+
+    /// Classify this error, based on its [HRESULT](Self::hresult).
+    pub fn kind(&self) -> ErrorKind {
+        let hru = self.hresult as u32;
+        let facility = (self.hresult >> 16) & 0x7FF;
+        let code     = hru & 0xFFFF;
+
+        #[deny(unreachable_patterns)]
+        match self.hresult {
+            WSL_E_DISTRO_NOT_FOUND  => ErrorKind::DistributionNotFound,
+            E_INVALIDARG            => ErrorKind::InvalidArgument,
+            _other => match (facility, code) {
+                (FACILITY_WIN32, ERROR_NOT_FOUND)       => ErrorKind::DistributionNotFound,
+                (FACILITY_WIN32, ERROR_ALREADY_EXISTS)  => ErrorKind::AlreadyExists,
+                (FACILITY_WIN32, ERROR_FILE_EXISTS)     => ErrorKind::AlreadyExists,
+                (FACILITY_WIN32, ERROR_INVALID_NAME)    => ErrorKind::InvalidName,
+                _other                                  => ErrorKind::Other(self.hresult),
+            },
+        }
+    }
+
+    /// Returns `true` if this error was caused by a distribution not being registered.
+    ///
+    /// Shorthand for <code>self.[kind](Self::kind)() == [ErrorKind::DistributionNotFound]</code>.  Useful for idempotent
+    /// teardown, where "already gone" should be treated as success:
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// match wsl.unregister_distribution("MyTestDistro") {
+    ///     Err(err) if err.is_not_found() => {}, // already unregistered
+    ///     other => other.unwrap(),
+    /// }
+    /// ```
+    pub fn is_not_found(&self) -> bool { self.kind() == ErrorKind::DistributionNotFound }
+
+    /// Returns `true` if this error was caused by something (e.g. a distribution) already existing.
+    ///
+    /// Shorthand for <code>self.[kind](Self::kind)() == [ErrorKind::AlreadyExists]</code>.
+    pub fn is_already_exists(&self) -> bool { self.kind() == ErrorKind::AlreadyExists }
+
+    /// The Win32 error code (e.g. `ERROR_FILE_NOT_FOUND` = `2`), if this error's [HRESULT](Self::hresult) is `FACILITY_WIN32`.
+    pub fn win32_code(&self) -> Option<u32> {
+        let hru = self.hresult as u32;
+        if HRESULT_FACILITY(self.hresult) == FACILITY_WIN32 && hru & 0x8000_0000 != 0 {
+            Some(hru & 0xFFFF)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_deref().map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
 
 impl Debug for Error {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("wslapi::Error")
             .field("hresult", &HR(self.hresult))
             .field("message", &self.message)
+            .field("command", &self.command)
+            .field("source", &self.source)
             .finish()
     }
 }
 
+/// Errors compare (and hash) equal if their [HRESULT](Error::hresult)s are equal, ignoring messages and commands.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool { self.hresult == other.hresult }
+}
+
+impl Eq for Error {}
+
+impl Hash for Error {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.hresult.hash(state) }
+}
+
 impl Display for Error {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         Display::fmt(&self.message, fmt)
@@ -33,12 +187,40 @@ impl Display for Error {
 
 impl From<Error> for io::Error {
     fn from(err: Error) -> Self {
-        io::Error::new(hr2ek(err.hresult), err.message)
+        io::Error::new(hr2ek(err.hresult), err)
+    }
+}
+
+/// Maps the OS error code (or failing that, the [io::ErrorKind]) to a representative [HRESULT](Error::hresult),
+/// keeping `err` as the [source](std::error::Error::source).  An [io::Error] converted from an [Error] unwraps back
+/// into the original.
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        if err.get_ref().map_or(false, |inner| inner.is::<Error>()) {
+            return *err.into_inner().unwrap().downcast::<Error>().unwrap();
+        }
+        Error::new(io2hr(&err), err.to_string()).with_source(err)
     }
 }
 
 
 
+/// Best effort [io::Error] to [HRESULT] conversion (roughly the reverse of [hr2ek] for errors without an OS error code)
+pub(crate) fn io2hr(err: &io::Error) -> HRESULT {
+    if let Some(code) = err.raw_os_error() { return HRESULT_FROM_WIN32(code as u32) }
+    match err.kind() {
+        io::ErrorKind::InvalidInput         => E_INVALIDARG,
+        io::ErrorKind::NotFound             => HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND),
+        io::ErrorKind::AlreadyExists        => HRESULT_FROM_WIN32(ERROR_ALREADY_EXISTS),
+        io::ErrorKind::InvalidData          => HRESULT_FROM_WIN32(ERROR_INVALID_DATA),
+        io::ErrorKind::PermissionDenied     => E_ACCESSDENIED,
+        io::ErrorKind::UnexpectedEof        => HRESULT_FROM_WIN32(ERROR_HANDLE_EOF),
+        io::ErrorKind::BrokenPipe           => HRESULT_FROM_WIN32(ERROR_BROKEN_PIPE),
+        io::ErrorKind::TimedOut             => HRESULT_FROM_WIN32(WAIT_TIMEOUT),
+        _other                              => E_FAIL,
+    }
+}
+
 struct HR(HRESULT);
 impl Debug for HR {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
@@ -85,3 +267,61 @@ fn hr2ek(hr: HRESULT) -> io::ErrorKind {
         },
     }
 }
+
+#[test] fn win32_code() {
+    let err = |hresult| Error::new(hresult, String::new());
+    assert_eq!(err(HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND)).win32_code(), Some(ERROR_FILE_NOT_FOUND));
+    assert_eq!(err(E_INVALIDARG).win32_code(), Some(ERROR_INVALID_PARAMETER));
+    assert_eq!(err(E_FAIL).win32_code(), None);
+    assert_eq!(err(S_OK).win32_code(), None);
+}
+
+#[test] fn kind() {
+    let err = |hresult| Error::new(hresult, String::new());
+    assert_eq!(err(WSL_E_DISTRO_NOT_FOUND).kind(),                      ErrorKind::DistributionNotFound);
+    assert_eq!(err(HRESULT_FROM_WIN32(ERROR_NOT_FOUND)).kind(),         ErrorKind::DistributionNotFound);
+    assert_eq!(err(HRESULT_FROM_WIN32(ERROR_ALREADY_EXISTS)).kind(),    ErrorKind::AlreadyExists);
+    assert_eq!(err(HRESULT_FROM_WIN32(ERROR_INVALID_NAME)).kind(),      ErrorKind::InvalidName);
+    assert_eq!(err(E_INVALIDARG).kind(),                                ErrorKind::InvalidArgument);
+    assert_eq!(err(E_FAIL).kind(),                                      ErrorKind::Other(E_FAIL));
+    assert!( err(WSL_E_DISTRO_NOT_FOUND).is_not_found());
+    assert!( err(HRESULT_FROM_WIN32(ERROR_NOT_FOUND)).is_not_found());
+    assert!(!err(E_INVALIDARG).is_not_found());
+}
+
+#[test] fn eq_hash() {
+    use std::collections::HashSet;
+    let a = Error::new(E_INVALIDARG, "launch(...) failed: command contained '\\0'");
+    let b = Error::new(E_INVALIDARG, "something else entirely");
+    let c = Error::new(E_FAIL, "launch(...) failed: command contained '\\0'");
+    assert!(a.matches(E_INVALIDARG));
+    assert!(!a.matches(E_FAIL));
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert_eq!(vec![a, b, c].into_iter().collect::<HashSet<_>>().len(), 2);
+}
+
+#[test] fn source() {
+    use std::error::Error as _;
+    let io = io::Error::from_raw_os_error(ERROR_FILE_NOT_FOUND as _);
+    let err = Error::new(io2hr(&io), "launch(...) failed: unable to create stdin").with_source(io);
+    assert_eq!(err.hresult(), HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND));
+    let source = err.source().and_then(|source| source.downcast_ref::<io::Error>()).unwrap();
+    assert_eq!(source.raw_os_error(), Some(ERROR_FILE_NOT_FOUND as _));
+    assert!(Error::new(E_FAIL, "").source().is_none());
+}
+
+#[test] fn from_io() {
+    let err = Error::from(io::Error::from_raw_os_error(ERROR_ACCESS_DENIED as _));
+    assert_eq!(err.hresult(), E_ACCESSDENIED);
+
+    let err = Error::from(io::Error::new(io::ErrorKind::NotFound, "no such thing"));
+    assert_eq!(err.hresult(), HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND));
+    assert_eq!(err.to_string(), "no such thing");
+
+    let original = Error::new(WSL_E_DISTRO_NOT_FOUND, "launch(...) failed").with_command(OsStr::new("ls"));
+    let err = Error::from(io::Error::from(original));
+    assert!(err.is_not_found());
+    assert_eq!(err.to_string(), "launch(...) failed");
+    assert_eq!(err.command(), Some(OsStr::new("ls")));
+}
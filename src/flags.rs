@@ -1,7 +1,12 @@
 #![allow(non_camel_case_types)] // WSL_DISTRIBUTION_FLAGS
 
+use crate::Error;
+
+use winapi::shared::winerror::E_INVALIDARG;
+
 use std::fmt::{self, Debug, Formatter};
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub, SubAssign};
+use std::str::FromStr;
 
 
 
@@ -26,11 +31,48 @@ impl WSL_DISTRIBUTION_FLAGS {
     /// `C:` will be available under `/mnt/c`).
     pub const ENABLE_DRIVE_MOUNTING     : Self = Self(0x4);
 
+    /// An undocumented flag, not present in `wslapi.h`, but commonly set in real configurations.
+    ///
+    /// Appears to be set for WSL 2 distributions, but this is *not* officially documented, so it's deliberately
+    /// excluded from [VALID](Self::VALID) and [DEFAULT](Self::DEFAULT).  Named mainly so it can be preserved or
+    /// inspected explicitly instead of being silently dropped.
+    #[allow(non_upper_case_globals)]
+    pub const RESERVED_0x8              : Self = Self(0x8);
+
     /// All valid flags
     pub const VALID                     : Self = Self(0x7);
 
     /// Default flags (all valid flags)
     pub const DEFAULT                   : Self = Self(0x7);
+
+    /// The individual documented flags and their names (e.g. `("ENABLE_INTEROP", ENABLE_INTEROP)`), from lowest to highest bit.
+    ///
+    /// Used by [Debug] and [FromStr], so this is always in sync with their formatting and parsing.
+    pub const NAMED : &'static [(&'static str, Self)] = &[
+        ("ENABLE_INTEROP",          Self::ENABLE_INTEROP),
+        ("APPEND_NT_PATH",          Self::APPEND_NT_PATH),
+        ("ENABLE_DRIVE_MOUNTING",   Self::ENABLE_DRIVE_MOUNTING),
+    ];
+
+    /// Returns `true` if all flags set in `other` are also set in `self`.
+    pub const fn contains(self, other: Self) -> bool { self.0 & other.0 == other.0 }
+
+    /// Returns `true` if [ENABLE_INTEROP](Self::ENABLE_INTEROP) is set.
+    pub const fn interop_enabled(self) -> bool { self.contains(Self::ENABLE_INTEROP) }
+
+    /// Returns `true` if [APPEND_NT_PATH](Self::APPEND_NT_PATH) is set.
+    pub const fn append_nt_path(self) -> bool { self.contains(Self::APPEND_NT_PATH) }
+
+    /// Returns `true` if [ENABLE_DRIVE_MOUNTING](Self::ENABLE_DRIVE_MOUNTING) is set.
+    pub const fn drive_mounting_enabled(self) -> bool { self.contains(Self::ENABLE_DRIVE_MOUNTING) }
+
+    /// Iterate over each individual set flag, from lowest to highest bit.
+    ///
+    /// Unknown bits are yielded as single-bit values too.
+    pub fn iter(&self) -> impl Iterator<Item = Self> {
+        let bits = self.0;
+        (0 .. 32).map(|bit| 1u32 << bit).filter(move |flag| bits & flag != 0).map(Self)
+    }
 }
 
 impl BitAnd for WSL_DISTRIBUTION_FLAGS {
@@ -51,6 +93,22 @@ impl BitOrAssign for WSL_DISTRIBUTION_FLAGS {
     fn bitor_assign(&mut self, rhs: Self) { self.0 |= rhs.0; }
 }
 
+/// Flips *all* 32 bits, not just [VALID](Self::VALID) ones.  Mask with [VALID](Self::VALID) if that's not what you want.
+impl Not for WSL_DISTRIBUTION_FLAGS {
+    type Output = Self;
+    fn not(self) -> Self { Self(!self.0) }
+}
+
+/// Set difference: flags in `self` that are *not* in `rhs`.
+impl Sub for WSL_DISTRIBUTION_FLAGS {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self { Self(self.0 & !rhs.0) }
+}
+
+impl SubAssign for WSL_DISTRIBUTION_FLAGS {
+    fn sub_assign(&mut self, rhs: Self) { self.0 &= !rhs.0; }
+}
+
 impl Default for WSL_DISTRIBUTION_FLAGS {
     fn default() -> Self { Self::DEFAULT }
 }
@@ -63,34 +121,157 @@ impl From<WSL_DISTRIBUTION_FLAGS> for u32 {
     fn from(value: WSL_DISTRIBUTION_FLAGS) -> Self { value.0 }
 }
 
+/// The format is part of this crate's stable contract, and always round-trips through [FromStr]:
+///
+/// - `WSL_DISTRIBUTION_FLAGS::NONE` if no bits are set
+/// - `WSL_DISTRIBUTION_FLAGS::FLAG` if exactly one name (or hex literal for unknown bits) is needed
+/// - `WSL_DISTRIBUTION_FLAGS::(FLAG|FLAG|0x...)` otherwise, with `DEFAULT` replacing all three documented flags,
+///   and any bits outside [VALID](Self::VALID) (including [RESERVED_0x8](Self::RESERVED_0x8)) combined into a
+///   single uppercase hex literal at the end
 impl Debug for WSL_DISTRIBUTION_FLAGS {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         if self.0 == 0 {
             write!(fmt, "WSL_DISTRIBUTION_FLAGS::NONE")
         } else {
-            write!(fmt, "WSL_DISTRIBUTION_FLAGS::")?;
-            let n = if self.0 == 0x7 { 1 } else { (self.0 & 0x7).count_ones() + (self.0 & !0x7).min(1) };
-            if n > 1 { write!(fmt, "(")?; }
-            let mut prev = false;
-            if self.0 & 0x7 == 7 {
-                prev = true;
-                write!(fmt, "DEFAULT")?;
+            let mut parts = Vec::new();
+            if self.contains(Self::DEFAULT) {
+                parts.push(String::from("DEFAULT"));
             } else {
-                if self.0 & 0x1 != 0 { if prev { write!(fmt, "|")?; } prev = true; write!(fmt, "ENABLE_INTEROP")?; }
-                if self.0 & 0x2 != 0 { if prev { write!(fmt, "|")?; } prev = true; write!(fmt, "APPEND_NT_PATH")?; }
-                if self.0 & 0x4 != 0 { if prev { write!(fmt, "|")?; } prev = true; write!(fmt, "ENABLE_DRIVE_MOUNTING")?; }
+                parts.extend(Self::NAMED.iter().filter(|(_, flag)| self.contains(*flag)).map(|(name, _)| String::from(*name)));
             }
             let invalid = self.0 & !Self::VALID.0;
-            if invalid != 0 { if prev { write!(fmt, "|")?; } write!(fmt, "0x{:X}", invalid)?; }
-            if n > 1 { write!(fmt, ")")?; }
-            Ok(())
+            if invalid != 0 { parts.push(format!("0x{:X}", invalid)); }
+
+            if parts.len() > 1 {
+                write!(fmt, "WSL_DISTRIBUTION_FLAGS::({})", parts.join("|"))
+            } else {
+                write!(fmt, "WSL_DISTRIBUTION_FLAGS::{}", parts[0])
+            }
         }
     }
 }
 
+/// Parses `|`-separated flag names (case-insensitive, e.g. `enable_interop|APPEND_NT_PATH`), including `NONE`,
+/// `DEFAULT`, `VALID`, and `RESERVED_0x8`, as well as hex literals (e.g. `0x8`) for unknown bits.
+///
+/// The [Debug] format (e.g. `WSL_DISTRIBUTION_FLAGS::(DEFAULT|0x8)`) round-trips losslessly.
+impl FromStr for WSL_DISTRIBUTION_FLAGS {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let err = |why| Error::new(E_INVALIDARG, format!("WSL_DISTRIBUTION_FLAGS::from_str({:?}) failed: {}", s, why));
+        // u32::from_str_radix alone would accept a leading sign (e.g. "0x+5")
+        let parse_hex = |hex: &str| if hex.bytes().all(|b| b.is_ascii_hexdigit()) { u32::from_str_radix(hex, 16).ok() } else { None };
+
+        let mut flags = s.trim();
+        if flags.get(..24).map_or(false, |prefix| prefix.eq_ignore_ascii_case("WSL_DISTRIBUTION_FLAGS::")) { flags = &flags[24..]; }
+        if flags.starts_with('(') && flags.ends_with(')') { flags = &flags[1..flags.len()-1]; }
+
+        let mut result = Self::NONE;
+        for token in flags.split('|').map(str::trim) {
+            if let Some(&(_, flag)) = Self::NAMED.iter().find(|(name, _)| token.eq_ignore_ascii_case(name)) {
+                result |= flag;
+                continue;
+            }
+            result |= match token {
+                t if t.eq_ignore_ascii_case("NONE")                           => Self::NONE,
+                t if t.eq_ignore_ascii_case("VALID")                          => Self::VALID,
+                t if t.eq_ignore_ascii_case("DEFAULT")                        => Self::DEFAULT,
+                t if t.eq_ignore_ascii_case("RESERVED_0x8")                   => Self::RESERVED_0x8,
+                t if t.get(..2).map_or(false, |x| x.eq_ignore_ascii_case("0x")) => Self(parse_hex(&t[2..]).ok_or_else(|| err(format!("invalid hex literal {:?}", t)))?),
+                ""                                                            => return Err(err(String::from("expected a flag name"))),
+                t                                                             => return Err(err(format!("unrecognized flag {:?}", t))),
+            };
+        }
+        Ok(result)
+    }
+}
+
 #[test] fn fmt_debug() {
     assert_eq!("WSL_DISTRIBUTION_FLAGS::NONE",                              format!("{:?}", WSL_DISTRIBUTION_FLAGS::NONE));
     assert_eq!("WSL_DISTRIBUTION_FLAGS::(ENABLE_INTEROP|APPEND_NT_PATH)",   format!("{:?}", WSL_DISTRIBUTION_FLAGS::ENABLE_INTEROP | WSL_DISTRIBUTION_FLAGS::APPEND_NT_PATH));
     assert_eq!("WSL_DISTRIBUTION_FLAGS::DEFAULT",                           format!("{:?}", WSL_DISTRIBUTION_FLAGS::DEFAULT));
     assert_eq!("WSL_DISTRIBUTION_FLAGS::(DEFAULT|0xFFFFFFF8)",              format!("{:?}", WSL_DISTRIBUTION_FLAGS(!0)));
 }
+
+#[test] fn iter() {
+    assert_eq!(WSL_DISTRIBUTION_FLAGS::NONE.iter().count(), 0);
+    assert_eq!(WSL_DISTRIBUTION_FLAGS::DEFAULT.iter().collect::<Vec<_>>(), [WSL_DISTRIBUTION_FLAGS::ENABLE_INTEROP, WSL_DISTRIBUTION_FLAGS::APPEND_NT_PATH, WSL_DISTRIBUTION_FLAGS::ENABLE_DRIVE_MOUNTING]);
+    assert_eq!(WSL_DISTRIBUTION_FLAGS(0x80000009).iter().collect::<Vec<_>>(), [WSL_DISTRIBUTION_FLAGS(0x1), WSL_DISTRIBUTION_FLAGS(0x8), WSL_DISTRIBUTION_FLAGS(0x80000000)]);
+    assert_eq!(WSL_DISTRIBUTION_FLAGS(!0).iter().count(), 32);
+}
+
+#[test] fn predicates() {
+    use WSL_DISTRIBUTION_FLAGS as F;
+    const _ : () = assert!(F::DEFAULT.interop_enabled()); // usable in const contexts
+    assert!(F::DEFAULT.append_nt_path() && F::DEFAULT.drive_mounting_enabled());
+    assert!(!F::NONE.interop_enabled() && !F::NONE.append_nt_path() && !F::NONE.drive_mounting_enabled());
+    assert!(!F::RESERVED_0x8.interop_enabled());
+    let f = F::ENABLE_INTEROP | F::ENABLE_DRIVE_MOUNTING;
+    assert!(f.interop_enabled() && !f.append_nt_path() && f.drive_mounting_enabled());
+}
+
+#[test] fn ops() {
+    use WSL_DISTRIBUTION_FLAGS as F;
+    assert_eq!(F::DEFAULT & !F::APPEND_NT_PATH, F::ENABLE_INTEROP | F::ENABLE_DRIVE_MOUNTING);
+    assert_eq!(F::DEFAULT - F::APPEND_NT_PATH,  F::ENABLE_INTEROP | F::ENABLE_DRIVE_MOUNTING);
+    assert_eq!(!F::NONE, F(!0));
+    let mut f = F(0xF);
+    f -= F::VALID;
+    assert_eq!(f, F(0x8));
+    assert!( F::DEFAULT.contains(F::ENABLE_INTEROP | F::APPEND_NT_PATH));
+    assert!( F::DEFAULT.contains(F::NONE));
+    assert!(!F::ENABLE_INTEROP.contains(F::DEFAULT));
+}
+
+#[test] fn from_str() {
+    use WSL_DISTRIBUTION_FLAGS as F;
+    let parse = |s: &str| s.parse::<F>().ok();
+    assert_eq!(parse("NONE"),                               Some(F::NONE));
+    assert_eq!(parse("default"),                            Some(F::DEFAULT));
+    assert_eq!(parse("ENABLE_INTEROP|APPEND_NT_PATH"),      Some(F::ENABLE_INTEROP | F::APPEND_NT_PATH));
+    assert_eq!(parse(" enable_interop | 0x8 "),             Some(F(0x9)));
+    assert_eq!(parse("DEFAULT|RESERVED_0x8"),               Some(F(0xF)));
+    assert_eq!(parse("0xFFFFFFFF"),                         Some(F(!0)));
+    assert_eq!(parse(""),                                   None);
+    assert_eq!(parse("ENABLE_INTEROP|"),                    None);
+    assert_eq!(parse("ENABLE_INTEROPS"),                    None);
+    assert_eq!(parse("0x"),                                 None);
+    assert_eq!(parse("0x100000000"),                        None);
+    assert_eq!(parse("0x+5"),                               None);
+    assert_eq!(parse("0x-5"),                               None);
+    assert_eq!(parse("0\u{e9}"),                             None);
+    for &bits in [0, 1, 2, 3, 4, 5, 6, 7, 8, 0xF, 0x80000001, !0].iter() {
+        let flags = F(bits);
+        assert_eq!(parse(&format!("{:?}", flags)), Some(flags), "{:?} didn't round trip", flags);
+    }
+}
+
+#[test] fn fmt_debug_round_trip() {
+    use WSL_DISTRIBUTION_FLAGS as F;
+    let debug = |bits| format!("{:?}", F(bits));
+    assert_eq!(debug(0x0),          "WSL_DISTRIBUTION_FLAGS::NONE");
+    assert_eq!(debug(0x1),          "WSL_DISTRIBUTION_FLAGS::ENABLE_INTEROP");
+    assert_eq!(debug(0x7),          "WSL_DISTRIBUTION_FLAGS::DEFAULT");
+    assert_eq!(debug(0x8),          "WSL_DISTRIBUTION_FLAGS::0x8");
+    assert_eq!(debug(0x9),          "WSL_DISTRIBUTION_FLAGS::(ENABLE_INTEROP|0x8)");
+    assert_eq!(debug(0xF),          "WSL_DISTRIBUTION_FLAGS::(DEFAULT|0x8)");
+    assert_eq!(debug(0x80000005),   "WSL_DISTRIBUTION_FLAGS::(ENABLE_INTEROP|ENABLE_DRIVE_MOUNTING|0x80000000)");
+
+    let highs = [0, 0x8, 0x10, 0x18, 0x8000_0000, 0xFFFF_FFF8];
+    for &high in highs.iter() {
+        for low in 0 ..= 0x7 {
+            let flags = F(high | low);
+            assert_eq!(format!("{:?}", flags).parse::<F>().ok(), Some(flags), "{:?} didn't round trip", flags);
+        }
+    }
+}
+
+#[test] fn named() {
+    use WSL_DISTRIBUTION_FLAGS as F;
+    assert_eq!(F::NAMED.iter().fold(F::NONE, |all, &(_, flag)| all | flag), F::VALID);
+    for &(name, flag) in F::NAMED.iter() {
+        assert_eq!(flag.iter().count(), 1, "{} isn't a single bit", name);
+        assert_eq!(format!("{:?}", flag), format!("WSL_DISTRIBUTION_FLAGS::{}", name));
+        assert_eq!(name.parse::<F>().ok(), Some(flag));
+    }
+}
@@ -1,26 +1,31 @@
 #![allow(non_snake_case)]
 
 use crate::{Error, Result};
-use crate::{Configuration, Process, Stdio};
+use crate::error::BoxError;
+use crate::{Command, Configuration, ConfigurationBuilder, Output, PipedProcess, Process, Stdio, StdioKind};
 use crate::WSL_DISTRIBUTION_FLAGS;
 
 use winapi::shared::minwindef::{BOOL, DWORD};
 use winapi::shared::ntdef::{HANDLE, PCWSTR, PSTR, ULONG};
-use winapi::shared::winerror::{SUCCEEDED, HRESULT, E_INVALIDARG};
+use winapi::shared::winerror::{SUCCEEDED, HRESULT, HRESULT_FROM_WIN32, E_FAIL, E_INVALIDARG, ERROR_ALREADY_EXISTS, ERROR_INVALID_NAME, ERROR_NO_UNICODE_TRANSLATION, ERROR_NOT_FOUND};
 
 use std::convert::TryInto;
-use std::ffi::OsStr;
-use std::fmt::Display;
-use std::io;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read};
 use std::os::windows::ffi::OsStrExt;
 use std::path::Path;
 use std::ptr::null_mut;
+use std::time::{Duration, Instant};
 
 // https://github.com/microsoft/WSL/issues/4645 - Wsl* may throw on null pointers, so avoid passing null pointers
 
 
 
 /// A loaded `wslapi.dll` or `api-ms-win-wsl-api-l1-1-0.dll` instance
+///
+/// This is just a table of function pointers, resolved once when loaded and immutable afterwards.  The DLL is never
+/// unloaded, so [Library] is cheap to [Clone], and is [Send] + [Sync]: share it between threads freely.
+#[derive(Clone)]
 pub struct Library {
     WslIsDistributionRegistered:        unsafe fn (distributionName: PCWSTR) -> BOOL,
     WslRegisterDistribution:            unsafe fn (distributionName: PCWSTR, tarGzFilename: PCWSTR) -> HRESULT,
@@ -32,10 +37,26 @@ pub struct Library {
 }
 
 impl Library {
-    /// Attempt to load `wslapi.dll`
+    /// Attempt to load `wslapi.dll`, falling back on `api-ms-win-wsl-api-l1-1-0.dll`
     pub fn new() -> io::Result<Self> {
-        // fallback on api-ms-win-wsl-api-l1-1-0.dll etc.?
-        let lib = minidl::Library::load("wslapi.dll")?;
+        let wslapi = match Self::from_path("wslapi.dll") {
+            Ok(lib) => return Ok(lib),
+            Err(err) => err,
+        };
+        let api_set = match Self::from_path("api-ms-win-wsl-api-l1-1-0.dll") {
+            Ok(lib) => return Ok(lib),
+            Err(err) => err,
+        };
+        Err(io::Error::new(wslapi.kind(), format!("unable to load wslapi.dll ({}) or api-ms-win-wsl-api-l1-1-0.dll ({})", wslapi, api_set)))
+    }
+
+    /// Attempt to load a specific `wslapi.dll`-compatible library, such as a stub for testing.
+    ///
+    /// `path` is searched for as per [LoadLibraryW] if it's not an absolute path.
+    ///
+    /// [LoadLibraryW]:                 https://docs.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryw
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let lib = minidl::Library::load(path)?;
         unsafe{Ok(Self{
             WslIsDistributionRegistered:        lib.sym("WslIsDistributionRegistered\0")?,
             WslRegisterDistribution:            lib.sym("WslRegisterDistribution\0")?,
@@ -64,8 +85,30 @@ impl Library {
     ///
     /// [WslIsDistributionRegistered]:  https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslisdistributionregistered
     pub fn is_distribution_registered(&self, distribution_name: impl AsRef<OsStr>) -> bool {
-        let distribution_name = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        !distribution_name[..distribution_name.len()-1].contains(&0) && unsafe { (self.WslIsDistributionRegistered)(distribution_name.as_ptr()) } != 0
+        match to_wide_name(distribution_name.as_ref(), &String::new) {
+            Ok(wname)   => 0 != unsafe { (self.WslIsDistributionRegistered)(wname.as_ptr()) },
+            Err(_)      => false, // can't be registered under an invalid name
+        }
+    }
+
+    /// Enumerates the names of distributions that are currently registered with the Windows Subsystem for Linux (WSL).
+    ///
+    /// Combines [registry::distribution_names] with [Library::is_distribution_registered], so only names the DLL agrees
+    /// are live are returned.  Registry errors are skipped - use [registry::distribution_names] directly if you care.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// for distro in wsl.registered_distributions() {
+    ///     println!("{}", distro.to_string_lossy());
+    /// }
+    /// ```
+    ///
+    /// [registry::distribution_names]:     crate::registry::distribution_names
+    /// [Library::is_distribution_registered]:  #method.is_distribution_registered
+    pub fn registered_distributions(&self) -> impl Iterator<Item = OsString> + '_ {
+        crate::registry::distribution_names().filter_map(|name| name.ok()).filter(move |name| self.is_distribution_registered(name))
     }
 
     /// Registers a new distribution with the Windows Subsystem for Linux (WSL).
@@ -82,8 +125,9 @@ impl Library {
     ///
     /// ### Returns
     ///
-    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters
-    /// - `Err(Error)`  - if `distribution_name` already existed
+    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters or invalid UTF-16
+    /// - `Err(Error)`  - if `distribution_name` was otherwise invalid (see [validate_distribution_name])
+    /// - `Err(Error)`  - if `distribution_name` already existed ([Error::is_already_exists])
     /// - `Err(Error)`  - if `tar_gz_filename` contained `'\0'` characters
     /// - `Err(Error)`  - if `tar_gz_filename` wasn't an absolute path?
     /// - `Err(Error)`  - if `tar_gz_filename` wasn't a valid path
@@ -98,13 +142,111 @@ impl Library {
     ///
     /// [WslRegisterDistribution]:  https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslregisterdistribution
     pub fn register_distribution(&self, distribution_name: impl AsRef<OsStr>, tar_gz_filename: impl AsRef<Path>) -> Result<()> {
-        let wname = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        let wpath = tar_gz_filename.as_ref().as_os_str().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        if wname[..wname.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("register_distribution({:?}, {:?}) failed: distribution_name contained '\0'", distribution_name.as_ref(), tar_gz_filename.as_ref()) }); }
-        if wpath[..wpath.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("register_distribution({:?}, {:?}) failed: tar_gz_filename contained '\0'",  distribution_name.as_ref(), tar_gz_filename.as_ref()) }); }
+        let context = || format!("register_distribution({:?}, {:?})", distribution_name.as_ref(), tar_gz_filename.as_ref());
+        let wname = to_wide_name(distribution_name.as_ref(), &context)?;
+        check_new_distribution_name(distribution_name.as_ref(), &context)?;
+        let wpath = to_wide_checked(tar_gz_filename.as_ref().as_os_str(), "tar_gz_filename", &context)?;
 
         let hr = unsafe { (self.WslRegisterDistribution)(wname.as_ptr(), wpath.as_ptr()) };
-        if !SUCCEEDED(hr) { return Err(Error { hresult: hr, message: format!("WslRegisterDistribution({:?}, {:?}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), tar_gz_filename.as_ref(), hr) }); }
+        if hr == HRESULT_FROM_WIN32(ERROR_ALREADY_EXISTS) { return Err(Error::new(hr, format!("WslRegisterDistribution({:?}, {:?}) failed: distribution_name already exists", distribution_name.as_ref(), tar_gz_filename.as_ref()))); }
+        if !SUCCEEDED(hr) { return Err(Error::new(hr, format!("WslRegisterDistribution({:?}, {:?}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), tar_gz_filename.as_ref(), hr))); }
+        Ok(())
+    }
+
+    /// Imports a new distribution into the Windows Subsystem for Linux (WSL), via `wsl --import <Distro> <InstallLocation> <FileName>`.
+    ///
+    /// Unlike [register_distribution](#method.register_distribution), this lets you choose where the distribution is
+    /// installed.  `wslapi.dll` has no equivalent entry point, so this requires `wsl.exe` (typically found in `%WINDIR%\System32`) on `%PATH%`.
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name`   - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    /// * `tar_gz_filename`     - Path to a .tar.gz file containing the file system of the distribution to import.
+    /// * `install_location`    - Directory to install the distribution's file system into (`rootfs` or `ext4.vhdx`).
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `distribution_name` was invalid (see [validate_distribution_name])
+    /// - `Err(Error)`  - if `wsl.exe` couldn't be launched
+    /// - `Err(Error)`  - if `wsl.exe --import ...` failed (e.g. `distribution_name` already existed, `tar_gz_filename` wasn't found, ...)
+    /// - `Ok(())`      - otherwise
+    ///
+    /// ### See Also
+    ///
+    /// - [register_distribution](#method.register_distribution) - the `wslapi.dll` equivalent, with odd install location limitations
+    /// - [wsl --import](https://docs.microsoft.com/en-us/windows/wsl/basic-commands#import-a-new-distribution)
+    pub fn import_distribution(&self, distribution_name: impl AsRef<OsStr>, tar_gz_filename: impl AsRef<Path>, install_location: impl AsRef<Path>) -> Result<()> {
+        check_new_distribution_name(distribution_name.as_ref(), &|| format!("import_distribution({:?}, {:?}, {:?})", distribution_name.as_ref(), tar_gz_filename.as_ref(), install_location.as_ref()))?;
+        crate::wsl_exe::run(&["--import".as_ref(), distribution_name.as_ref(), install_location.as_ref().as_os_str(), tar_gz_filename.as_ref().as_os_str()])?;
+        Ok(())
+    }
+
+    /// Exports a distribution's file system to a tarball, via `wsl --export <Distro> <FileName>`.
+    ///
+    /// `wslapi.dll` has no equivalent entry point, so this requires `wsl.exe` (typically found in `%WINDIR%\System32`) on `%PATH%`.
+    /// The resulting file can be imported again with [import_distribution](#method.import_distribution).
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name`   - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    /// * `tar_filename`        - Path of the .tar file to create.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `wsl.exe` couldn't be launched
+    /// - `Err(Error)`  - if `wsl.exe --export ...` failed (e.g. `distribution_name` didn't exist, `tar_filename` wasn't writable, ...)
+    /// - `Ok(())`      - otherwise
+    ///
+    /// ### See Also
+    ///
+    /// - [import_distribution](#method.import_distribution)
+    /// - [wsl --export](https://docs.microsoft.com/en-us/windows/wsl/basic-commands#export-a-distribution)
+    pub fn export_distribution(&self, distribution_name: impl AsRef<OsStr>, tar_filename: impl AsRef<Path>) -> Result<()> {
+        crate::wsl_exe::run(&["--export".as_ref(), distribution_name.as_ref(), tar_filename.as_ref().as_os_str()])?;
+        Ok(())
+    }
+
+    /// Terminates a running distribution (and for WSL2, its utility VM if nothing else is using it), via `wsl --terminate <Distro>`.
+    ///
+    /// Any WSL processes running in the distribution are killed.  The distribution will be restarted on the next launch.
+    /// `wslapi.dll` has no equivalent entry point, so this requires `wsl.exe` (typically found in `%WINDIR%\System32`) on `%PATH%`.
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name`   - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `wsl.exe` couldn't be launched
+    /// - `Err(Error)`  - if `wsl.exe --terminate ...` failed (e.g. `distribution_name` didn't exist)
+    /// - `Ok(())`      - otherwise, including if the distribution wasn't running
+    ///
+    /// ### See Also
+    ///
+    /// - [wsl --terminate](https://docs.microsoft.com/en-us/windows/wsl/basic-commands#terminate)
+    pub fn terminate_distribution(&self, distribution_name: impl AsRef<OsStr>) -> Result<()> {
+        crate::wsl_exe::run(&["--terminate".as_ref(), distribution_name.as_ref()])?;
+        Ok(())
+    }
+
+    /// Terminates *all* running distributions and the WSL2 lightweight utility VM, via `wsl --shutdown`.
+    ///
+    /// This is a global, cross-distribution operation: every WSL process of the current user is killed, not just those
+    /// launched through this [Library].  Useful for a clean reset between integration test suites.  `wslapi.dll` has no
+    /// equivalent entry point, so this spawns `wsl.exe --shutdown` (`wsl.exe` is typically found in `%WINDIR%\System32`.)
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `wsl.exe` couldn't be launched
+    /// - `Err(Error)`  - if `wsl.exe --shutdown` failed (e.g. an older inbox `wsl.exe` without `--shutdown`)
+    /// - `Ok(())`      - otherwise, including if nothing was running
+    ///
+    /// ### See Also
+    ///
+    /// - [terminate_distribution](#method.terminate_distribution) - to stop a single distribution
+    /// - [wsl --shutdown](https://docs.microsoft.com/en-us/windows/wsl/basic-commands#shutdown)
+    pub fn shutdown(&self) -> Result<()> {
+        crate::wsl_exe::run(&["--shutdown".as_ref()])?;
         Ok(())
     }
 
@@ -116,8 +258,8 @@ impl Library {
     ///
     /// ### Returns
     ///
-    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters
-    /// - `Err(Error)`  - if `distribution_name` didn't exist?
+    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters or invalid UTF-16
+    /// - `Err(Error)`  - if `distribution_name` wasn't registered ([Error::is_not_found])
     /// - `Err(Error)`  - if [WslUnregisterDistribution] failed
     /// - `Ok(())`      - otherwise
     ///
@@ -125,13 +267,13 @@ impl Library {
     ///
     /// - [WslUnregisterDistribution] - the underlying API
     ///
+    /// [Error::is_not_found]:              crate::Error::is_not_found
     /// [WslUnregisterDistribution]:        https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslunregisterdistribution
     pub fn unregister_distribution(&self, distribution_name: impl AsRef<OsStr>) -> Result<()> {
-        let wname = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        if wname[..wname.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("unregister_distribution({:?}) failed: distribution_name contained '\0'", distribution_name.as_ref()) }); }
+        let wname = to_wide_name(distribution_name.as_ref(), &|| format!("unregister_distribution({:?})", distribution_name.as_ref()))?;
 
         let hr = unsafe { (self.WslUnregisterDistribution)(wname.as_ptr()) };
-        if !SUCCEEDED(hr) { return Err(Error { hresult: hr, message: format!("WslUnregisterDistribution({:?}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), hr) }); }
+        if !SUCCEEDED(hr) { return Err(Error::new(hr, format!("WslUnregisterDistribution({:?}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), hr))); }
         Ok(())
     }
 
@@ -141,27 +283,61 @@ impl Library {
     ///
     /// * `distribution_name`       - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
     /// * `default_uid`             - The Linux user ID to use when launching new WSL sessions for this distribution.
-    /// * `wsl_distribution_flags`  - Flags specifying what behavior to use for this distribution.
+    ///   Typically `0` (root) or a regular user's UID (`1000`+ on most distributions.)  Not validated, since the valid
+    ///   range depends on the distribution's `/etc/login.defs`.
+    /// * `wsl_distribution_flags`  - Flags specifying what behavior to use for this distribution.  Passed through as-is,
+    ///   including any unknown bits - see [configure_distribution_checked](#method.configure_distribution_checked) to
+    ///   reject those instead of persisting them into the registry.
     ///
     /// ### Returns
     ///
-    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters
+    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters or invalid UTF-16
     /// - `Err(Error)`  - if `distribution_name` didn't exist?
     /// - `Err(Error)`  - if [WslConfigureDistribution] otherwise failed (invalid uid? invalid flags?)
     /// - `Ok(())`      - otherwise
     ///
-    /// ### Returns
+    /// ### See Also
+    ///
+    /// - [configure_distribution_checked](#method.configure_distribution_checked) - to validate flags first
+    /// - [configure](#method.configure) - to modify only some settings
+    /// - [WslConfigureDistribution] - the underlying API
     ///
     /// [WslConfigureDistribution]:     https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslconfiguredistribution
     pub fn configure_distribution(&self, distribution_name: impl AsRef<OsStr>, default_uid: ULONG, wsl_distribution_flags: WSL_DISTRIBUTION_FLAGS) -> Result<()> {
-        let wname = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        if wname[..wname.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("configure_distribution({:?}) failed: distribution_name contained '\0'", distribution_name.as_ref()) }); }
+        let wname = to_wide_name(distribution_name.as_ref(), &|| format!("configure_distribution({:?})", distribution_name.as_ref()))?;
 
         let hr = unsafe { (self.WslConfigureDistribution)(wname.as_ptr(), default_uid, wsl_distribution_flags) };
-        if !SUCCEEDED(hr) { return Err(Error { hresult: hr, message: format!("WslConfigureDistribution({:?}, {}, {:?}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), default_uid, wsl_distribution_flags, hr) }); }
+        if !SUCCEEDED(hr) { return Err(Error::new(hr, format!("WslConfigureDistribution({:?}, {}, {:?}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), default_uid, wsl_distribution_flags, hr))); }
         Ok(())
     }
 
+    /// Like [configure_distribution](#method.configure_distribution), but first rejects any `wsl_distribution_flags`
+    /// bits outside of `allowed`, so unknown bits aren't accidentally persisted into the registry.
+    ///
+    /// `allowed` is typically [WSL_DISTRIBUTION_FLAGS::VALID], or `VALID | RESERVED_0x8` to permit the undocumented
+    /// flag commonly set on WSL 2 distributions.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `wsl_distribution_flags` contained bits outside `allowed` ([ErrorKind::InvalidArgument])
+    /// - `Err(Error)`  - if [configure_distribution](#method.configure_distribution) failed
+    /// - `Ok(())`      - otherwise
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # use wslapi::*;
+    /// # let wsl = Library::new().unwrap();
+    /// let err = wsl.configure_distribution_checked("Ubuntu", 1000, WSL_DISTRIBUTION_FLAGS::from(0x10), WSL_DISTRIBUTION_FLAGS::VALID).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    /// ```
+    ///
+    /// [ErrorKind::InvalidArgument]:   crate::ErrorKind::InvalidArgument
+    pub fn configure_distribution_checked(&self, distribution_name: impl AsRef<OsStr>, default_uid: ULONG, wsl_distribution_flags: WSL_DISTRIBUTION_FLAGS, allowed: WSL_DISTRIBUTION_FLAGS) -> Result<()> {
+        check_flags(wsl_distribution_flags, allowed).map_err(|unknown| Error::new(E_INVALIDARG, format!("configure_distribution_checked({:?}, {}, {:?}, {:?}) failed: unknown flags {:?}", distribution_name.as_ref(), default_uid, wsl_distribution_flags, allowed, unknown)))?;
+        self.configure_distribution(distribution_name, default_uid, wsl_distribution_flags)
+    }
+
     /// Retrieves the current configuration of a distribution registered with the Windows Subsystem for Linux (WSL).
     ///
     /// ### Arguments
@@ -170,7 +346,7 @@ impl Library {
     ///
     /// ### Returns
     ///
-    /// - `Err(Error)` - if `distribution_name` contained `'\0'` characters
+    /// - `Err(Error)` - if `distribution_name` contained `'\0'` characters or invalid UTF-16
     /// - `Err(Error)` - if `distribution_name` didn't exist?
     /// - `Err(Error)` - if [WslGetDistributionConfiguration] failed
     /// - `Ok(Configuration { version, default_uid, flags, default_environment_variables })` - otherwise
@@ -182,17 +358,102 @@ impl Library {
     ///
     /// [WslGetDistributionConfiguration]:      https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wslgetdistributionconfiguration
     pub fn get_distribution_configuration(&self, distribution_name: impl AsRef<OsStr>) -> Result<Configuration> {
-        let wname = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        if wname[..wname.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("get_distribution_configuration({:?}, ...) failed: distribution_name contained '\0'", distribution_name.as_ref()) }); }
+        let wname = to_wide_name(distribution_name.as_ref(), &|| format!("get_distribution_configuration({:?}, ...)", distribution_name.as_ref()))?;
 
         let mut cfg = Configuration::default();
         let mut nvars = 0;
         let hr = unsafe { (self.WslGetDistributionConfiguration)(wname.as_ptr(), &mut cfg.version, &mut cfg.default_uid, &mut cfg.flags, &mut cfg.default_environment_variables.array, &mut nvars) };
         cfg.default_environment_variables.count = nvars.try_into().unwrap();
-        if !SUCCEEDED(hr) { return Err(Error { hresult: hr, message: format!("WslGetDistributionConfiguration({:?}, ...) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), hr) }); }
+        if !SUCCEEDED(hr) { return Err(Error::new(hr, format!("WslGetDistributionConfiguration({:?}, ...) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), hr))); }
         Ok(cfg)
     }
 
+    /// Retrieves the name and current configuration of the default distribution.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)` - if no default distribution is set ([ErrorKind::DistributionNotFound])
+    /// - `Err(Error)` - if the registry couldn't be read
+    /// - `Err(Error)` - if [get_distribution_configuration] failed
+    /// - `Ok((name, Configuration { ... }))` - otherwise
+    ///
+    /// ### See Also
+    ///
+    /// - [registry::default_distribution_name]
+    /// - [get_distribution_configuration]
+    ///
+    /// [ErrorKind::DistributionNotFound]:      crate::ErrorKind::DistributionNotFound
+    /// [registry::default_distribution_name]:  crate::registry::default_distribution_name
+    /// [get_distribution_configuration]:       #method.get_distribution_configuration
+    pub fn default_distribution_configuration(&self) -> Result<(OsString, Configuration)> {
+        let io_err = |err: io::Error| Error::new(crate::error::io2hr(&err), format!("default_distribution_configuration() failed: {}", err)).with_source(err);
+        let name = crate::registry::default_distribution_name().map_err(io_err)?;
+        let name = name.ok_or_else(|| Error::new(HRESULT_FROM_WIN32(ERROR_NOT_FOUND), "default_distribution_configuration() failed: no default distribution is set"))?;
+        let config = self.get_distribution_configuration(&name)?;
+        Ok((name, config))
+    }
+
+    /// Makes `distribution_name` the default distribution (the one a bare `wsl` invocation targets.)
+    ///
+    /// `wslapi.dll` has no equivalent, so this writes the distribution's GUID to
+    /// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\DefaultDistribution` directly, like `wsl --set-default`.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)` - if `distribution_name` contained `'\0'` characters or invalid UTF-16
+    /// - `Err(Error)` - if `distribution_name` isn't registered ([ErrorKind::DistributionNotFound])
+    /// - `Err(Error)` - if the registry couldn't be read or written
+    /// - `Ok(())`     - otherwise
+    ///
+    /// ### See Also
+    ///
+    /// - [registry::default_distribution_name]
+    ///
+    /// [ErrorKind::DistributionNotFound]:      crate::ErrorKind::DistributionNotFound
+    /// [registry::default_distribution_name]:  crate::registry::default_distribution_name
+    pub fn set_default_distribution(&self, distribution_name: impl AsRef<OsStr>) -> Result<()> {
+        let name = distribution_name.as_ref();
+        let context = || format!("set_default_distribution({:?})", name);
+        let _ = to_wide_name(name, &context)?;
+        let io_err = |err: io::Error| Error::new(crate::error::io2hr(&err), format!("{} failed: {}", context(), err)).with_source(err);
+        let guid = crate::registry::find_guid(name).map_err(io_err)?;
+        let guid = guid.ok_or_else(|| Error::new(crate::error::WSL_E_DISTRO_NOT_FOUND, format!("{} failed: distribution not registered", context())))?;
+        crate::registry::set_string_value(OsStr::new(crate::registry::LXSS), "DefaultDistribution", &guid).map_err(io_err)
+    }
+
+    /// Modify the configuration of a distribution registered with the Windows Subsystem for Linux (WSL),
+    /// starting from its current configuration.
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name` - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)` - if [get_distribution_configuration] failed
+    /// - `Ok(ConfigurationBuilder)` - otherwise
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// wsl.configure("Ubuntu").unwrap()
+    ///     .append_nt_path(false)
+    ///     .apply().unwrap();
+    /// ```
+    ///
+    /// ### See Also
+    ///
+    /// - [get_distribution_configuration] - reads the initial configuration
+    /// - [configure_distribution] - applies the modified configuration
+    ///
+    /// [get_distribution_configuration]:   #method.get_distribution_configuration
+    /// [configure_distribution]:           #method.configure_distribution
+    pub fn configure(&self, distribution_name: impl AsRef<OsStr>) -> Result<ConfigurationBuilder<'_>> {
+        let current = self.get_distribution_configuration(distribution_name.as_ref())?;
+        Ok(ConfigurationBuilder::new(self, distribution_name.as_ref().to_owned(), &current))
+    }
+
     /// Launches an interactive Windows Subsystem for Linux (WSL) process in the context of a particular distribution.
     /// This differs from [Library::launch] in that the end user will be able to interact with the newly-created process.
     ///
@@ -206,7 +467,7 @@ impl Library {
     ///
     /// ### Returns
     ///
-    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters
+    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters or invalid UTF-16
     /// - `Err(Error)`  - if `distribution_name` didn't exist?
     /// - `Err(Error)`  - if `command` contained `'\0'` characters
     /// - `Err(Error)`  - if [WslLaunchInteractive] otherwise failed
@@ -220,17 +481,88 @@ impl Library {
     /// [Library::launch]:      crate::Library::launch
     /// [WslLaunchInteractive]: https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunchinteractive
     pub fn launch_interactive(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool) -> Result<DWORD> {
-        let wname = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        let wcmd  = command.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        if wname[..wname.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("launch_interactive({:?}, {:?}, {}) failed: distribution_name contained '\0'",  distribution_name.as_ref(), command.as_ref(), use_current_working_directory) }); }
-        if wcmd [..wcmd .len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("launch_interactive({:?}, {:?}, {}) failed: command contained '\0'",            distribution_name.as_ref(), command.as_ref(), use_current_working_directory) }); }
+        let context = || format!("launch_interactive({:?}, {:?}, {})", distribution_name.as_ref(), command.as_ref(), use_current_working_directory);
+        let wname = to_wide_name(distribution_name.as_ref(), &context).map_err(|err| err.with_command(command.as_ref()))?;
+        let wcmd  = to_wide_checked(command.as_ref(), "command", &context).map_err(|err| err.with_command(command.as_ref()))?;
 
         let mut exit_code = 0;
         let hr = unsafe { (self.WslLaunchInteractive)(wname.as_ptr(), if command.as_ref().is_empty() { null_mut() } else { wcmd.as_ptr() }, use_current_working_directory as BOOL, &mut exit_code) };
-        if !SUCCEEDED(hr) { return Err(Error { hresult: hr, message: format!("WslLaunchInteractive({:?}, {:?}, {}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, hr) }); }
+        if !SUCCEEDED(hr) { return Err(Error::new(hr, format!("WslLaunchInteractive({:?}, {:?}, {}) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, hr)).with_command(command.as_ref())); }
         Ok(exit_code)
     }
 
+    /// Launches an interactive Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// with a command line built from individually quoted arguments.
+    ///
+    /// Each argument is quoted for a POSIX shell (e.g. `it's` becomes `'it'\''s'`), so spaces, quotes,
+    /// `$variables`, etc. are passed through literally instead of being interpreted by the shell.
+    /// An empty `args` launches the default shell.
+    ///
+    /// ### See Also
+    ///
+    /// - [Library::launch_interactive] - for details on the remaining arguments and return values
+    ///
+    /// [Library::launch_interactive]:  #method.launch_interactive
+    pub fn launch_interactive_args(&self, distribution_name: impl AsRef<OsStr>, args: impl IntoIterator<Item = impl AsRef<OsStr>>, use_current_working_directory: bool) -> Result<DWORD> {
+        self.launch_interactive(distribution_name, crate::shell::join(args), use_current_working_directory)
+    }
+
+    /// Launches an interactive Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// starting in the Linux directory `dir` (if `Some`.)
+    ///
+    /// `wslapi.dll` has no parameter for this, so the command is prefixed with `cd <dir> && `, with `dir` quoted for a
+    /// POSIX shell.  `dir` is a Linux path (e.g. `/mnt/c/Users`, not `C:\Users`), interpreted by the distribution's
+    /// shell relative to the starting directory selected by `use_current_working_directory`.  Since it's quoted, a
+    /// leading `~` is *not* expanded.  If `command` is empty, the user's `$SHELL` is launched in `dir`.
+    ///
+    /// ### See Also
+    ///
+    /// - [Library::launch_interactive] - for details on the remaining arguments and return values
+    ///
+    /// [Library::launch_interactive]:  #method.launch_interactive
+    pub fn launch_interactive_in(&self, distribution_name: impl AsRef<OsStr>, dir: Option<&str>, command: impl AsRef<OsStr>, use_current_working_directory: bool) -> Result<DWORD> {
+        match dir {
+            None        => self.launch_interactive(distribution_name, command, use_current_working_directory),
+            Some(dir)   => self.launch_interactive(distribution_name, crate::shell::cd(OsStr::new(dir), command.as_ref()), use_current_working_directory),
+        }
+    }
+
+    /// Launches an "interactive" Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// killing it if it hasn't exited within `timeout`.
+    ///
+    /// Unlike [Library::launch_interactive], this is built on [WslLaunch] with (duplicates of) the current process's
+    /// **STDIN**, **STDOUT**, and **STDERR** handles (see [Stdio::inherit_stdin] etc.) instead of [WslLaunchInteractive].
+    /// The process shares the same console, but isn't attached to it quite the same way: e.g. `Ctrl+C` may reach
+    /// both processes, and if the current process has no console (or redirected stdio), neither will the WSL process.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`          - if [Library::launch] failed
+    /// - `Err(Error)`          - if waiting on or killing the process failed
+    /// - `Ok(None)`            - if `timeout` elapsed, and the process was killed
+    /// - `Ok(Some(exit_code))` - if the process exited on its own within `timeout`
+    ///
+    /// ### See Also
+    ///
+    /// - [Library::launch_interactive] - for details on the remaining arguments
+    /// - [Process::wait_timeout]
+    ///
+    /// [Library::launch]:              #method.launch
+    /// [Library::launch_interactive]:  #method.launch_interactive
+    /// [Process::wait_timeout]:        crate::Process::wait_timeout
+    /// [WslLaunch]:                    https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    /// [WslLaunchInteractive]:         https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunchinteractive
+    pub fn launch_interactive_timeout(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool, timeout: Duration) -> Result<Option<DWORD>> {
+        let io_err = |what, err: io::Error| Error::new(crate::error::io2hr(&err), format!("launch_interactive_timeout({:?}, {:?}, {}, {:?}) failed: {}: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, timeout, what, err)).with_command(command.as_ref()).with_source(err);
+        let mut process = self.launch_with(distribution_name.as_ref(), command.as_ref(), use_current_working_directory, StdioKind::Inherit, StdioKind::Inherit, StdioKind::Inherit)?;
+        if let Some(status) = process.wait_timeout(timeout).map_err(|err| io_err("waiting for process exit", err))? {
+            return Ok(Some(status.code().unwrap_or(!0)));
+        }
+        process.kill().map_err(|err| io_err("killing process", err))?;
+        process.wait().map_err(|err| io_err("waiting for killed process", err))?;
+        Ok(None)
+    }
+
     /// Launches a Windows Subsystem for Linux (WSL) process in the context of a particular distribution.
     ///
     /// ### Arguments
@@ -246,7 +578,7 @@ impl Library {
     ///
     /// ### Returns
     ///
-    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters
+    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters or invalid UTF-16
     /// - `Err(Error)`  - if `distribution_name` didn't exist?
     /// - `Err(Error)`  - if `command` contained `'\0'` characters
     /// - `Err(Error)`  - if `stdin`, `stdout`, or `stderr` failed to convert to [Stdio]
@@ -271,23 +603,513 @@ impl Library {
         stdout:                         O,
         stderr:                         E,
     ) -> Result<Process> where
-        I : TryInto<Stdio>, I::Error : Display,
-        O : TryInto<Stdio>, O::Error : Display,
-        E : TryInto<Stdio>, E::Error : Display,
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+        O : TryInto<Stdio>, O::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+        E : TryInto<Stdio>, E::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
     {
         // https://github.com/microsoft/WSL-DistroLauncher/blob/540a593313f8abbc8ce3afe8ca98434e8a771798/DistroLauncher/DistributionInfo.cpp#L48
 
-        let wname = distribution_name.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        let wcmd  = command.as_ref().encode_wide().chain(Some(0)).collect::<Vec<_>>();
-        if wname[..wname.len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("launch({:?}, {:?}, {}, ...) failed: distribution_name contained '\0'",  distribution_name.as_ref(), command.as_ref(), use_current_working_directory) }); }
-        if wcmd [..wcmd .len()-1].contains(&0) { return Err(Error { hresult: E_INVALIDARG, message: format!("launch({:?}, {:?}, {}, ...) failed: command contained '\0'",            distribution_name.as_ref(), command.as_ref(), use_current_working_directory) }); }
-        let stdin  = stdin .try_into().map_err(|err| Error { hresult: E_INVALIDARG, message: format!("launch({:?}, {:?}, {}, ...) failed: failed to convert stdin: {}",  distribution_name.as_ref(), command.as_ref(), use_current_working_directory, err) })?;
-        let stdout = stdout.try_into().map_err(|err| Error { hresult: E_INVALIDARG, message: format!("launch({:?}, {:?}, {}, ...) failed: failed to convert stdout: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, err) })?;
-        let stderr = stderr.try_into().map_err(|err| Error { hresult: E_INVALIDARG, message: format!("launch({:?}, {:?}, {}, ...) failed: failed to convert stderr: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, err) })?;
+        let context = || format!("launch({:?}, {:?}, {}, ...)", distribution_name.as_ref(), command.as_ref(), use_current_working_directory);
+        let wname = to_wide_name(distribution_name.as_ref(), &context).map_err(|err| err.with_command(command.as_ref()))?;
+        let wcmd  = to_wide_checked(command.as_ref(), "command", &context).map_err(|err| err.with_command(command.as_ref()))?;
+        let convert_err = |stream, err: BoxError| Error::new(E_INVALIDARG, format!("{} failed: failed to convert {}: {}", context(), stream, err)).with_command(command.as_ref()).with_source(err);
+        let stdin  = stdin .try_into().map_err(|err| convert_err("stdin",  err.into()))?;
+        let stdout = stdout.try_into().map_err(|err| convert_err("stdout", err.into()))?;
+        let stderr = stderr.try_into().map_err(|err| convert_err("stderr", err.into()))?;
 
         let mut handle = null_mut();
+        let started = Instant::now();
         let hr = unsafe { (self.WslLaunch)(wname.as_ptr(), wcmd.as_ptr(), use_current_working_directory as BOOL, stdin.as_winapi_handle(), stdout.as_winapi_handle(), stderr.as_winapi_handle(), &mut handle) };
-        if !SUCCEEDED(hr) { return Err(Error { hresult: hr, message: format!("WslLaunch({:?}, {:?}, {}, ...) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, hr) }); }
-        Ok(Process { handle, stdin, stdout, stderr })
+        if !SUCCEEDED(hr) { return Err(Error::new(hr, format!("WslLaunch({:?}, {:?}, {}, ...) failed with HRESULT 0x{:08x}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, hr)).with_command(command.as_ref())); }
+        Ok(Process { handle, stdin, stdout, stderr, stdin_pipe: None, stdout_pipe: None, stderr_pipe: None, status: None, started })
     }
+
+    /// Launches a Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// with a command line built from individually quoted arguments.
+    ///
+    /// Each argument is quoted for a POSIX shell (e.g. `it's` becomes `'it'\''s'`), so spaces, quotes,
+    /// `$variables`, etc. are passed through literally instead of being interpreted by the shell.
+    /// An empty `args` launches the default shell.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// let args = ["ls", "-la", "/mnt/c/Program Files"];
+    /// wsl.launch_args("Ubuntu", &args, false, (), (), ()).unwrap().wait().unwrap();
+    /// ```
+    ///
+    /// ### See Also
+    ///
+    /// - [Library::launch] - for details on the remaining arguments and return values
+    ///
+    /// [Library::launch]:              #method.launch
+    pub fn launch_args<I, O, E>(
+        &self,
+        distribution_name:              impl AsRef<OsStr>,
+        args:                           impl IntoIterator<Item = impl AsRef<OsStr>>,
+        use_current_working_directory:  bool,
+        stdin:                          I,
+        stdout:                         O,
+        stderr:                         E,
+    ) -> Result<Process> where
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+        O : TryInto<Stdio>, O::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+        E : TryInto<Stdio>, E::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.launch(distribution_name, crate::shell::join(args), use_current_working_directory, stdin, stdout, stderr)
+    }
+
+    /// Launches a Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// with **STDERR** merged into **STDOUT** (like `2>&1`.)
+    ///
+    /// `combined_out` is converted to a [Stdio] and duplicated, so the child's **STDOUT** and **STDERR** share a single
+    /// underlying file or pipe - and file position - and interleaved output lands in the order it was written.  Each
+    /// duplicate is owned (and closed) independently by the returned [Process].
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// let log = std::fs::File::create("target/merged.txt").unwrap();
+    /// wsl.launch_merged("Ubuntu", "echo out; echo err >&2", false, (), log).unwrap().wait().unwrap();
+    /// ```
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `combined_out` failed to convert to [Stdio], or couldn't be duplicated
+    /// - `Err(Error)`  - if [Library::launch] failed
+    /// - `Ok(Process)` - if the WSL process that launched successfully
+    ///
+    /// ### See Also
+    ///
+    /// - [Library::launch] - for details on the remaining arguments and return values
+    /// - [Stdio::try_clone] - to merge streams yourself
+    ///
+    /// [Library::launch]:              #method.launch
+    pub fn launch_merged<I, O>(
+        &self,
+        distribution_name:              impl AsRef<OsStr>,
+        command:                        impl AsRef<OsStr>,
+        use_current_working_directory:  bool,
+        stdin:                          I,
+        combined_out:                   O,
+    ) -> Result<Process> where
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+        O : TryInto<Stdio>, O::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let stdout = combined_out.try_into().map_err(|err| { let err : BoxError = err.into(); Error::new(E_INVALIDARG, format!("launch_merged({:?}, {:?}, {}, ...) failed: failed to convert combined_out: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, err)).with_command(command.as_ref()).with_source(err) })?;
+        let stderr = stdout.try_clone().map_err(|err| Error::new(crate::error::io2hr(&err), format!("launch_merged({:?}, {:?}, {}, ...) failed: unable to duplicate combined_out: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, err)).with_command(command.as_ref()).with_source(err))?;
+        self.launch(distribution_name, command, use_current_working_directory, stdin, stdout, stderr)
+    }
+
+    /// Create a [Command] builder for launching a Windows Subsystem for Linux (WSL) process via [Library::launch].
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name`   - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    /// * `command`             - Command to execute. If no command is supplied, launches the default shell.
+    ///
+    /// ### See Also
+    ///
+    /// - [Command]
+    /// - [Library::launch] - the positional equivalent
+    ///
+    /// [Library::launch]:              #method.launch
+    pub fn command(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>) -> Command<'_> {
+        Command::new(self, distribution_name.as_ref(), command.as_ref())
+    }
+
+    /// Launches a Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// with **STDIN**, **STDOUT**, and **STDERR** connected to anonymous pipes.
+    ///
+    /// The parent's ends of the pipes are *not* inheritable, and the child's ends are closed as soon as
+    /// [WslLaunch] returns, so dropping [PipedProcess::stdin] will result in EOF for the child process, and
+    /// [PipedProcess::stdout] / [PipedProcess::stderr] will reach EOF once the child process exits.
+    ///
+    /// <span style="color: red">**Beware deadlocks:**</span>  pipe buffers are finite.  A child process blocked
+    /// writing to a full **STDOUT** pipe won't read more **STDIN**, nor exit.  Read **STDOUT** and **STDERR**
+    /// concurrently (e.g. from another thread) when streaming large amounts of data.
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name`   - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    /// * `command`             - Command to execute. If no command is supplied, launches the default shell.
+    /// * `use_current_working_directory` - Governs whether or not the launched process should inherit
+    ///   the calling process's working directory. If `false`, the process is started in the WSL
+    ///   default user's home directory ("~").
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if `distribution_name` contained `'\0'` characters or invalid UTF-16
+    /// - `Err(Error)`  - if `distribution_name` didn't exist?
+    /// - `Err(Error)`  - if `command` contained `'\0'` characters
+    /// - `Err(Error)`  - if the pipes couldn't be created
+    /// - `Err(Error)`  - if [WslLaunch] otherwise failed
+    /// - `Ok(PipedProcess)` - if the WSL process that launched successfully
+    ///
+    /// ### See Also
+    ///
+    /// - [PipedProcess]
+    /// - [Library::launch] - for files, bytes, or custom handles
+    /// - [Library::launch_with] - to pipe only some streams, leaving the pipes on [Process] ([Process::stdin_mut] etc.)
+    /// - [WslLaunch] - the underlying API
+    ///
+    /// [Library::launch]:              #method.launch
+    /// [Library::launch_with]:         #method.launch_with
+    /// [Process::stdin_mut]:           crate::Process::stdin_mut
+    /// [WslLaunch]:                    https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn launch_piped(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool) -> Result<PipedProcess> {
+        let mut process = self.launch_with(distribution_name, command, use_current_working_directory, StdioKind::Piped, StdioKind::Piped, StdioKind::Piped)?;
+        let stdin  = process.take_stdin() .expect("launch_with(..., StdioKind::Piped, ...) didn't pipe stdin");
+        let stdout = process.take_stdout().expect("launch_with(..., StdioKind::Piped, ...) didn't pipe stdout");
+        let stderr = process.take_stderr().expect("launch_with(..., StdioKind::Piped, ...) didn't pipe stderr");
+        Ok(PipedProcess { process, stdin, stdout, stderr })
+    }
+
+    /// Launches a Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// with an explicit [StdioKind] for each of **STDIN**, **STDOUT**, and **STDERR**.
+    ///
+    /// Unlike [Library::launch], the intent is obvious at the call site, and the three streams don't need to share a
+    /// type.  Any [StdioKind::Piped] streams are available via [Process::stdin_mut] etc., which also describes
+    /// deadlock hazards.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # use wslapi::*;
+    /// # use std::io::Read;
+    /// # let wsl = Library::new().unwrap();
+    /// let mut process = wsl.launch_with("Ubuntu", "cat", false, StdioKind::Bytes(b"testing 123".to_vec()), StdioKind::Piped, StdioKind::Inherit).unwrap();
+    /// let mut stdout = String::new();
+    /// process.take_stdout().unwrap().read_to_string(&mut stdout).unwrap();
+    /// assert_eq!(stdout, "testing 123");
+    /// ```
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if any pipes or files couldn't be created
+    /// - `Err(Error)`  - if [Library::launch] failed
+    /// - `Ok(Process)` - if the WSL process that launched successfully
+    ///
+    /// [Library::launch]:              #method.launch
+    /// [Process::stdin_mut]:           crate::Process::stdin_mut
+    pub fn launch_with(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool, stdin: StdioKind, stdout: StdioKind, stderr: StdioKind) -> Result<Process> {
+        let io_err = |stream, err: io::Error| Error::new(crate::error::io2hr(&err), format!("launch_with({:?}, {:?}, {}, ...) failed: unable to create {}: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, stream, err)).with_command(command.as_ref()).with_source(err);
+        let (stdin,  stdin_pipe ) = stdin .into_input()                        .map_err(|err| io_err("stdin",  err))?;
+        let (stdout, stdout_pipe) = stdout.into_output(Stdio::inherit_stdout)  .map_err(|err| io_err("stdout", err))?;
+        let (stderr, stderr_pipe) = stderr.into_output(Stdio::inherit_stderr)  .map_err(|err| io_err("stderr", err))?;
+
+        let mut process = self.launch(distribution_name.as_ref(), command.as_ref(), use_current_working_directory, stdin, stdout, stderr)?;
+
+        // The child process has inherited its own copies of any pipe handles.  Close ours, or we'd never see EOF on stdout/stderr.
+        if stdin_pipe .is_some() { process.stdin  = Stdio::null(); }
+        if stdout_pipe.is_some() { process.stdout = Stdio::null(); }
+        if stderr_pipe.is_some() { process.stderr = Stdio::null(); }
+
+        process.stdin_pipe  = stdin_pipe;
+        process.stdout_pipe = stdout_pipe;
+        process.stderr_pipe = stderr_pipe;
+        Ok(process)
+    }
+
+    /// Launches a Windows Subsystem for Linux (WSL) process in the context of a particular distribution,
+    /// waits for it to exit, and collects everything it wrote to **STDOUT** and **STDERR**.
+    ///
+    /// The collected bytes are raw, unaffected by the Windows console code page: see [Encoding] for decoding them.
+    ///
+    /// ### Arguments
+    ///
+    /// * `distribution_name`   - Unique name representing a distribution (for example, "Fabrikam.Distro.10.01").
+    /// * `command`             - Command to execute. If no command is supplied, launches the default shell.
+    /// * `use_current_working_directory` - Governs whether or not the launched process should inherit
+    ///   the calling process's working directory. If `false`, the process is started in the WSL
+    ///   default user's home directory ("~").
+    /// * `stdin`               - Handle to use for **STDIN**.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`  - if [Library::launch] failed
+    /// - `Err(Error)`  - if the **STDOUT** / **STDERR** pipes couldn't be created or read
+    /// - `Err(Error)`  - if waiting for the process to exit failed
+    /// - `Ok(Output)`  - otherwise, even if the process exited with a non-zero exit code
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// let output = wsl.output("Ubuntu", "echo testing 123", false, ()).unwrap();
+    /// assert!(output.status.success());
+    /// assert_eq!(output.stdout, b"testing 123\n");
+    /// ```
+    ///
+    /// ### See Also
+    ///
+    /// - [Output]
+    /// - [Encoding]
+    /// - [Library::launch]
+    /// - [Library::launch_piped] - for streaming instead of collecting
+    ///
+    /// [Encoding]:                     crate::Encoding
+    /// [Library::launch]:              #method.launch
+    /// [Library::launch_piped]:        #method.launch_piped
+    pub fn output<I>(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool, stdin: I) -> Result<Output> where
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let io_err = |what, err: io::Error| Error::new(crate::error::io2hr(&err), format!("output({:?}, {:?}, {}, ...) failed: {}: {}", distribution_name.as_ref(), command.as_ref(), use_current_working_directory, what, err)).with_command(command.as_ref()).with_source(err);
+        let (child_stdout, mut stdout) = crate::pipe::output_pipe().map_err(|err| io_err("unable to create stdout pipe", err))?;
+        let (child_stderr, mut stderr) = crate::pipe::output_pipe().map_err(|err| io_err("unable to create stderr pipe", err))?;
+
+        let mut process = self.launch(distribution_name.as_ref(), command.as_ref(), use_current_working_directory, stdin, child_stdout, child_stderr)?;
+        process.stdout = Stdio::null(); // see launch_piped
+        process.stderr = Stdio::null();
+
+        // Read stderr on another thread, lest the process block on a full stderr pipe while we wait for stdout EOF.
+        let stderr = std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            stderr.read_to_end(&mut buffer).map(|_| buffer)
+        });
+        let mut stdout_buffer = Vec::new();
+        let stdout = stdout.read_to_end(&mut stdout_buffer).map(|_| stdout_buffer);
+        let stderr = stderr.join().expect("wslapi::Library::output stderr thread panicked");
+
+        let status = process.wait().map_err(|err| io_err("waiting for process exit", err))?;
+        let stdout = stdout.map_err(|err| io_err("reading stdout", err))?;
+        let stderr = stderr.map_err(|err| io_err("reading stderr", err))?;
+        Ok(Output { status, stdout, stderr })
+    }
+
+    /// Runs `command` in a distribution's default user's home directory ("~") with no **STDIN**, and returns everything
+    /// it wrote to **STDOUT**, decoded as UTF-8.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`      - if [Library::output] failed (e.g. `distribution_name` didn't exist)
+    /// - `Err(Error)`      - if the command exited unsuccessfully (the error message includes its trimmed **STDERR**)
+    /// - `Err(Error)`      - if **STDOUT** wasn't valid UTF-8 (see [Library::run_utf8_lossy])
+    /// - `Ok(String)`      - **STDOUT**, untrimmed
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// # let wsl = wslapi::Library::new().unwrap();
+    /// let uname = wsl.run_utf8("Ubuntu", "uname -s").unwrap();
+    /// assert_eq!(uname, "Linux\n");
+    /// assert!(wsl.run_utf8("Ubuntu", "echo oops >&2; exit 1").unwrap_err().to_string().contains("oops"));
+    /// ```
+    ///
+    /// [Library::output]:              #method.output
+    /// [Library::run_utf8_lossy]:      #method.run_utf8_lossy
+    pub fn run_utf8(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>) -> Result<String> {
+        let (distribution_name, command) = (distribution_name.as_ref(), command.as_ref());
+        let output = self.run_checked("run_utf8", distribution_name, command)?;
+        String::from_utf8(output.stdout).map_err(|err| Error::new(HRESULT_FROM_WIN32(ERROR_NO_UNICODE_TRANSLATION), format!("run_utf8({:?}, {:?}) failed: stdout wasn't valid UTF-8: {}", distribution_name, command, err)).with_command(command).with_source(err))
+    }
+
+    /// Like [Library::run_utf8], but invalid UTF-8 sequences in **STDOUT** are replaced with `U+FFFD` instead of failing.
+    ///
+    /// [Library::run_utf8]:            #method.run_utf8
+    pub fn run_utf8_lossy(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>) -> Result<String> {
+        let output = self.run_checked("run_utf8_lossy", distribution_name.as_ref(), command.as_ref())?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn run_checked(&self, fn_name: &str, distribution_name: &OsStr, command: &OsStr) -> Result<Output> {
+        let output = self.output(distribution_name, command, false, ())?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::new(E_FAIL, format!("{}({:?}, {:?}) failed with {}: {}", fn_name, distribution_name, command, output.status, stderr.trim())).with_command(command));
+        }
+        Ok(output)
+    }
+
+    /// Retrieves the login shell (e.g. `/bin/bash`) of a distribution's default user, from `/etc/passwd` (or another
+    /// NSS source) via `getent passwd "$(id -u)" | cut -d: -f7`.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(Error)`      - if [Library::output] failed (e.g. `distribution_name` didn't exist)
+    /// - `Err(Error)`      - if the command failed, or printed an empty shell (e.g. no `getent` in the distribution)
+    /// - `Ok(String)`      - the trimmed path of the login shell
+    ///
+    /// [Library::output]:              #method.output
+    pub fn default_shell(&self, distribution_name: impl AsRef<OsStr>) -> Result<String> {
+        let command = OsStr::new(r#"getent passwd "$(id -u)" | cut -d: -f7"#);
+        let output = self.output(distribution_name.as_ref(), command, false, ())?;
+        let shell = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        if !output.status.success() || shell.is_empty() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::new(E_FAIL, format!("default_shell({:?}) failed with {}: {}", distribution_name.as_ref(), output.status, stderr.trim())).with_command(command));
+        }
+        Ok(shell)
+    }
+}
+
+/// Returns `Err(unknown)` if `flags` has any bits set outside of `allowed`.
+fn check_flags(flags: WSL_DISTRIBUTION_FLAGS, allowed: WSL_DISTRIBUTION_FLAGS) -> std::result::Result<(), WSL_DISTRIBUTION_FLAGS> {
+    let unknown = flags - allowed;
+    if unknown == WSL_DISTRIBUTION_FLAGS::NONE { Ok(()) } else { Err(unknown) }
+}
+
+/// Convert `value` into a `'\0'`-terminated wide string, failing with `E_INVALIDARG` if it contains `'\0'`.
+///
+/// `context` describes the failing call (e.g. `launch("Ubuntu", "sh", false, ...)`), and is only evaluated on error.
+fn to_wide_checked(value: &OsStr, param: &str, context: &dyn Fn() -> String) -> Result<Vec<u16>> {
+    let wide = value.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    if wide[..wide.len()-1].contains(&0) { return Err(Error::new(E_INVALIDARG, format!("{} failed: {} contained '\0'", context(), param))); }
+    Ok(wide)
+}
+
+/// Like [to_wide_checked], but for `distribution_name`s, which additionally must be valid UTF-16.
+fn to_wide_name(distribution_name: &OsStr, context: &dyn Fn() -> String) -> Result<Vec<u16>> {
+    let wide = distribution_name.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    if let Some(why) = invalid_distribution_name(&wide) { return Err(Error::new(E_INVALIDARG, format!("{} failed: distribution_name {}", context(), why))); }
+    Ok(wide)
+}
+
+/// The maximum length, in UTF-16 code units, accepted by [validate_distribution_name].
+pub const MAX_DISTRIBUTION_NAME_LEN : usize = 255;
+
+/// Check that `name` is acceptable as the name of a *new* distribution, before asking WSL to register it.
+///
+/// Called by [Library::register_distribution] and [Library::import_distribution], or call it yourself to validate
+/// user input (e.g. from a form) up front.  These rules are a conservative subset of what WSL enforces (which varies
+/// between versions), so passing doesn't guarantee WSL will accept `name` - but failing means it almost certainly
+/// wouldn't, or that the distribution would be awkward to use (e.g. via `wsl -d <name>` or as a directory name.)
+///
+/// ### Returns
+///
+/// - `Err(Error)` - if `name` was empty
+/// - `Err(Error)` - if `name` contained `'\0'` characters (can't be passed to `wslapi.dll` at all)
+/// - `Err(Error)` - if `name` contained invalid UTF-16 (e.g. unpaired surrogates)
+/// - `Err(Error)` - if `name` contained path separators (`/`, `\`) or other characters invalid in Windows file names (`:*?"<>|`)
+/// - `Err(Error)` - if `name` contained control characters (including tabs and newlines)
+/// - `Err(Error)` - if `name` started or ended with whitespace
+/// - `Err(Error)` - if `name` was longer than [MAX_DISTRIBUTION_NAME_LEN] UTF-16 code units
+/// - `Ok(())`     - otherwise
+///
+/// All errors are [ErrorKind::InvalidName].
+///
+/// ### Example
+///
+/// ```rust
+/// assert!(wslapi::validate_distribution_name("Ubuntu-20.04").is_ok());
+/// assert!(wslapi::validate_distribution_name("").is_err());
+/// assert!(wslapi::validate_distribution_name("../Ubuntu").is_err());
+/// ```
+///
+/// [Library::register_distribution]:   crate::Library::register_distribution
+/// [Library::import_distribution]:     crate::Library::import_distribution
+/// [ErrorKind::InvalidName]:           crate::ErrorKind::InvalidName
+pub fn validate_distribution_name(name: impl AsRef<OsStr>) -> Result<()> {
+    check_new_distribution_name(name.as_ref(), &|| format!("validate_distribution_name({:?})", name.as_ref()))
+}
+
+fn check_new_distribution_name(name: &OsStr, context: &dyn Fn() -> String) -> Result<()> {
+    let wname = name.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let why = invalid_distribution_name(&wname).or_else(|| {
+        let name = name.to_str()?; // invalid UTF-16 already rejected above
+        if name.is_empty() {
+            Some("was empty")
+        } else if name.contains(['/', '\\']) {
+            Some("contained a path separator")
+        } else if name.contains([':', '*', '?', '"', '<', '>', '|']) {
+            Some("contained a character invalid in file names")
+        } else if name.contains(char::is_control) {
+            Some("contained a control character")
+        } else if name.trim() != name {
+            Some("started or ended with whitespace")
+        } else if wname.len() - 1 > MAX_DISTRIBUTION_NAME_LEN {
+            Some("was too long")
+        } else {
+            None
+        }
+    });
+    match why {
+        Some(why)   => Err(Error::new(HRESULT_FROM_WIN32(ERROR_INVALID_NAME), format!("{} failed: distribution_name {}", context(), why))),
+        None        => Ok(()),
+    }
+}
+
+/// Why `wname` (a `'\0'`-terminated wide string) isn't a valid distribution name, if it isn't.
+///
+/// Catches these up front, rather than letting WSL reject them with an opaque `HRESULT`.
+fn invalid_distribution_name(wname: &[u16]) -> Option<&'static str> {
+    let wname = &wname[..wname.len()-1];
+    if wname.contains(&0) {
+        Some("contained '\0'")
+    } else if std::char::decode_utf16(wname.iter().copied()).any(|ch| ch.is_err()) {
+        Some("contained an invalid UTF-16 sequence")
+    } else {
+        None
+    }
+}
+
+#[test] fn send_sync_clone() {
+    fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+    assert_send_sync_clone::<Library>();
+}
+
+#[test] fn distribution_name_validation() {
+    let wide = |s: &[u16]| s.iter().copied().chain(Some(0)).collect::<Vec<_>>();
+    assert_eq!(invalid_distribution_name(&wide(&[])),                           None);
+    assert_eq!(invalid_distribution_name(&wide(&"Ubuntu".encode_utf16().collect::<Vec<_>>())), None);
+    assert_eq!(invalid_distribution_name(&wide(&[0x55, 0, 0x55])),              Some("contained '\0'"));
+    assert_eq!(invalid_distribution_name(&wide(&[0x55, 0xD800])),               Some("contained an invalid UTF-16 sequence"));
+    assert_eq!(invalid_distribution_name(&wide(&[0xDC00, 0x55])),               Some("contained an invalid UTF-16 sequence"));
+    assert_eq!(invalid_distribution_name(&wide(&[0xD83D, 0xDE00])),             None); // 😀
+}
+
+#[test] fn new_distribution_name_validation() {
+    let why = |name: &str| validate_distribution_name(name).err().map(|err| { assert_eq!(err.kind(), crate::ErrorKind::InvalidName); err.to_string() });
+    assert_eq!(why("Ubuntu-20.04"),             None);
+    assert_eq!(why("Fabrikam.Distro.10.01"),    None);
+    assert_eq!(why("My Distro"),                None);
+    assert_eq!(why(""),                         Some(String::from(r#"validate_distribution_name("") failed: distribution_name was empty"#)));
+    assert!(why("Ubu\0ntu").unwrap().ends_with("contained '\0'"));
+    assert!(why("../Ubuntu").unwrap().ends_with("contained a path separator"));
+    assert!(why("C:Ubuntu").unwrap().ends_with("contained a character invalid in file names"));
+    assert!(why("Ubuntu\n").unwrap().ends_with("contained a control character"));
+    assert!(why(" Ubuntu").unwrap().ends_with("started or ended with whitespace"));
+    assert_eq!(why(&"x".repeat(MAX_DISTRIBUTION_NAME_LEN)), None);
+    assert!(why(&"x".repeat(MAX_DISTRIBUTION_NAME_LEN + 1)).unwrap().ends_with("was too long"));
+}
+
+#[test] fn flags_validation() {
+    use WSL_DISTRIBUTION_FLAGS as F;
+    assert_eq!(check_flags(F::DEFAULT,                      F::VALID), Ok(()));
+    assert_eq!(check_flags(F::NONE,                         F::VALID), Ok(()));
+    assert_eq!(check_flags(F::DEFAULT | F::RESERVED_0x8,    F::VALID), Err(F::RESERVED_0x8));
+    assert_eq!(check_flags(F::DEFAULT | F::RESERVED_0x8,    F::VALID | F::RESERVED_0x8), Ok(()));
+    assert_eq!(check_flags(F::from(0x31),                   F::VALID), Err(F::from(0x30)));
+}
+
+#[test] #[ignore = "modifies real WSL configuration"] fn configure_builder_keeps_unknown_flags() {
+    // Re-applying a distribution's current configuration must succeed even if it has undocumented flags set.  Run with
+    // `cargo test -- --ignored` on a machine where rewriting every registered distribution's configuration is acceptable.
+    let wsl = match Library::new() { Ok(wsl) => wsl, Err(_) => return }; // WSL not installed
+    for distro in wsl.registered_distributions() {
+        let before = wsl.get_distribution_configuration(&distro).unwrap();
+        wsl.configure(&distro).unwrap().apply().unwrap();
+        let after = wsl.get_distribution_configuration(&distro).unwrap();
+        assert_eq!((before.default_uid, before.flags), (after.default_uid, after.flags), "{:?}", distro);
+    }
+}
+
+#[test] fn to_wide() {
+    use std::os::windows::ffi::OsStringExt;
+    let context = || String::from("launch(...)");
+
+    assert_eq!(to_wide_checked(OsStr::new("sh"), "command", &context).unwrap(), [0x73, 0x68, 0]);
+    let err = to_wide_checked(OsStr::new("s\0h"), "command", &context).unwrap_err();
+    assert_eq!(err.hresult(), E_INVALIDARG);
+    assert_eq!(err.to_string(), "launch(...) failed: command contained '\0'");
+
+    assert_eq!(to_wide_name(OsStr::new("Ubuntu"), &context).unwrap().len(), 7);
+    let err = to_wide_name(OsStr::new("Ubu\0ntu"), &context).unwrap_err();
+    assert_eq!(err.hresult(), E_INVALIDARG);
+    assert_eq!(err.to_string(), "launch(...) failed: distribution_name contained '\0'");
+    let err = to_wide_name(&std::ffi::OsString::from_wide(&[0x55, 0xD800]), &context).unwrap_err();
+    assert_eq!(err.hresult(), E_INVALIDARG);
+    assert_eq!(err.to_string(), "launch(...) failed: distribution_name contained an invalid UTF-16 sequence");
 }
@@ -0,0 +1,168 @@
+//! `feature = "mock"`: [MockWsl]
+
+use crate::{Configuration, EnvironmentVariables, Error, ExitStatus, Output, Result, Stdio, Wsl, WSL_DISTRIBUTION_FLAGS};
+use crate::error::WSL_E_DISTRO_NOT_FOUND;
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::ULONG;
+use winapi::shared::winerror::{HRESULT_FROM_WIN32, ERROR_ALREADY_EXISTS};
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+
+
+/// A fake, in-memory [Wsl] implementation for tests, which records calls and returns canned results.
+///
+/// Distributions "registered" via [Wsl::register_distribution] or [MockWsl::with_distribution] behave roughly like
+/// real ones: they can be configured, queried, and unregistered.  [Wsl::output] and [Wsl::launch_interactive] return
+/// whatever was set up via [MockWsl::set_output] for that command, or a successful, empty [Output] by default.
+/// Launching in an unregistered distribution fails with [ErrorKind::DistributionNotFound].
+///
+/// ### Example
+///
+/// ```rust
+/// use wslapi::*;
+///
+/// let wsl = MockWsl::new().with_distribution("Ubuntu");
+/// wsl.set_output("uname -r", 0, "5.10.16.3-microsoft-standard-WSL2\n", "");
+///
+/// let output = wsl.output("Ubuntu", "uname -r", false, ()).unwrap();
+/// assert_eq!(output.stdout, b"5.10.16.3-microsoft-standard-WSL2\n");
+/// assert!(wsl.output("Debian", "uname -r", false, ()).is_err());
+/// assert_eq!(wsl.calls().len(), 2);
+/// ```
+///
+/// [ErrorKind::DistributionNotFound]:  crate::ErrorKind::DistributionNotFound
+#[derive(Default)]
+pub struct MockWsl {
+    state: Mutex<MockState>,
+}
+
+/// A call recorded by [MockWsl].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(missing_docs)] // variants mirror the Wsl methods of the same name
+pub enum MockCall {
+    IsDistributionRegistered(OsString),
+    RegisterDistribution(OsString, PathBuf),
+    UnregisterDistribution(OsString),
+    ConfigureDistribution(OsString, ULONG, WSL_DISTRIBUTION_FLAGS),
+    GetDistributionConfiguration(OsString),
+    LaunchInteractive(OsString, OsString, bool),
+    Output(OsString, OsString, bool),
+}
+
+#[derive(Default)]
+struct MockState {
+    calls:          Vec<MockCall>,
+    distributions:  BTreeMap<OsString, Configuration>,
+    outputs:        HashMap<OsString, Output>,
+}
+
+impl MockWsl {
+    /// Create a [MockWsl] with no distributions registered.
+    pub fn new() -> Self { Self::default() }
+
+    /// Register a distribution named `distribution_name`, with a default [Configuration] (WSL 2, root, default flags.)
+    pub fn with_distribution(self, distribution_name: impl AsRef<OsStr>) -> Self {
+        self.lock().distributions.insert(distribution_name.as_ref().to_owned(), default_configuration());
+        self
+    }
+
+    /// Set the canned result of running `command` (in any distribution) via [Wsl::output] or [Wsl::launch_interactive].
+    pub fn set_output(&self, command: impl AsRef<OsStr>, exit_code: DWORD, stdout: impl Into<Vec<u8>>, stderr: impl Into<Vec<u8>>) {
+        let output = Output { status: ExitStatus::from_code(exit_code), stdout: stdout.into(), stderr: stderr.into() };
+        self.lock().outputs.insert(command.as_ref().to_owned(), output);
+    }
+
+    /// All calls made so far, in order.
+    pub fn calls(&self) -> Vec<MockCall> { self.lock().calls.clone() }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn launch(&self, call: MockCall, distribution_name: &OsStr, command: &OsStr) -> Result<Output> {
+        let mut state = self.lock();
+        state.calls.push(call);
+        if !state.distributions.contains_key(distribution_name) { return Err(not_found(distribution_name).with_command(command)) }
+        Ok(state.outputs.get(command).cloned().unwrap_or(Output { status: ExitStatus::from_code(0), stdout: Vec::new(), stderr: Vec::new() }))
+    }
+}
+
+impl Wsl for MockWsl {
+    fn is_distribution_registered(&self, distribution_name: impl AsRef<OsStr>) -> bool {
+        let distribution_name = distribution_name.as_ref();
+        let mut state = self.lock();
+        state.calls.push(MockCall::IsDistributionRegistered(distribution_name.to_owned()));
+        state.distributions.contains_key(distribution_name)
+    }
+
+    fn register_distribution(&self, distribution_name: impl AsRef<OsStr>, tar_gz_filename: impl AsRef<Path>) -> Result<()> {
+        let distribution_name = distribution_name.as_ref();
+        let mut state = self.lock();
+        state.calls.push(MockCall::RegisterDistribution(distribution_name.to_owned(), tar_gz_filename.as_ref().to_owned()));
+        if state.distributions.contains_key(distribution_name) {
+            return Err(Error::new(HRESULT_FROM_WIN32(ERROR_ALREADY_EXISTS), format!("MockWsl::register_distribution({:?}, ...) failed: distribution_name already exists", distribution_name)));
+        }
+        state.distributions.insert(distribution_name.to_owned(), default_configuration());
+        Ok(())
+    }
+
+    fn unregister_distribution(&self, distribution_name: impl AsRef<OsStr>) -> Result<()> {
+        let distribution_name = distribution_name.as_ref();
+        let mut state = self.lock();
+        state.calls.push(MockCall::UnregisterDistribution(distribution_name.to_owned()));
+        state.distributions.remove(distribution_name).map(|_| ()).ok_or_else(|| not_found(distribution_name))
+    }
+
+    fn configure_distribution(&self, distribution_name: impl AsRef<OsStr>, default_uid: ULONG, wsl_distribution_flags: WSL_DISTRIBUTION_FLAGS) -> Result<()> {
+        let distribution_name = distribution_name.as_ref();
+        let mut state = self.lock();
+        state.calls.push(MockCall::ConfigureDistribution(distribution_name.to_owned(), default_uid, wsl_distribution_flags));
+        let config = state.distributions.get_mut(distribution_name).ok_or_else(|| not_found(distribution_name))?;
+        config.default_uid  = default_uid;
+        config.flags        = wsl_distribution_flags;
+        Ok(())
+    }
+
+    fn get_distribution_configuration(&self, distribution_name: impl AsRef<OsStr>) -> Result<Configuration> {
+        let distribution_name = distribution_name.as_ref();
+        let mut state = self.lock();
+        state.calls.push(MockCall::GetDistributionConfiguration(distribution_name.to_owned()));
+        state.distributions.get(distribution_name).cloned().ok_or_else(|| not_found(distribution_name))
+    }
+
+    fn launch_interactive(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool) -> Result<DWORD> {
+        let (distribution_name, command) = (distribution_name.as_ref(), command.as_ref());
+        let call = MockCall::LaunchInteractive(distribution_name.to_owned(), command.to_owned(), use_current_working_directory);
+        Ok(self.launch(call, distribution_name, command)?.status.code().unwrap_or(!0))
+    }
+
+    fn output<I>(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool, _stdin: I) -> Result<Output> where
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let (distribution_name, command) = (distribution_name.as_ref(), command.as_ref());
+        let call = MockCall::Output(distribution_name.to_owned(), command.to_owned(), use_current_working_directory);
+        self.launch(call, distribution_name, command)
+    }
+}
+
+fn default_configuration() -> Configuration {
+    Configuration { version: 2, default_uid: 0, flags: WSL_DISTRIBUTION_FLAGS::DEFAULT, default_environment_variables: EnvironmentVariables::new() }
+}
+
+fn not_found(distribution_name: &OsStr) -> Error {
+    Error::new(WSL_E_DISTRO_NOT_FOUND, format!("MockWsl: distribution {:?} not registered", distribution_name))
+}
+
+#[test] fn unregister_nonexistent() {
+    let wsl = MockWsl::new().with_distribution("Ubuntu");
+    assert!(wsl.unregister_distribution("Nonexistent").unwrap_err().is_not_found());
+    wsl.unregister_distribution("Ubuntu").unwrap();
+    assert!(wsl.unregister_distribution("Ubuntu").unwrap_err().is_not_found());
+    assert_eq!(wsl.calls().len(), 3);
+}
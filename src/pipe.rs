@@ -0,0 +1,134 @@
+use crate::Stdio;
+
+use winapi::shared::minwindef::{FALSE, TRUE};
+use winapi::shared::ntdef::HANDLE;
+use winapi::um::handleapi::SetHandleInformation;
+use winapi::um::minwinbase::SECURITY_ATTRIBUTES;
+use winapi::um::namedpipeapi::CreatePipe;
+use winapi::um::winbase::HANDLE_FLAG_INHERIT;
+
+use std::convert::TryInto;
+use std::fmt::Arguments;
+use std::fs::File;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, IntoRawHandle, RawHandle};
+use std::ptr::null_mut;
+use std::thread::JoinHandle;
+
+
+
+/// The parent's (writable) end of an anonymous pipe connected to a WSL process's **STDIN**.
+///
+/// Dropping this closes the pipe, which the WSL process will observe as EOF.
+///
+/// ### See Also
+///
+/// - [Library::launch_piped]
+///
+/// [Library::launch_piped]:        crate::Library::launch_piped
+pub struct PipeWriter(File);
+
+/// The parent's (readable) end of an anonymous pipe connected to a WSL process's **STDOUT** or **STDERR**.
+///
+/// ### See Also
+///
+/// - [Library::launch_piped]
+///
+/// [Library::launch_piped]:        crate::Library::launch_piped
+pub struct PipeReader(File);
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.0.write(buf) }
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> { self.0.write_vectored(bufs) }
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> { self.0.write_all(buf) }
+    fn write_fmt(&mut self, fmt: Arguments) -> io::Result<()> { self.0.write_fmt(fmt) }
+    fn flush(&mut self) -> io::Result<()> { self.0.flush() }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.0.read(buf) }
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> { self.0.read_vectored(bufs) }
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> { self.0.read_to_end(buf) }
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> { self.0.read_to_string(buf) }
+}
+
+impl AsHandle     for PipeWriter { fn as_handle(&self) -> BorrowedHandle<'_> { self.0.as_handle() } }
+impl AsHandle     for PipeReader { fn as_handle(&self) -> BorrowedHandle<'_> { self.0.as_handle() } }
+impl AsRawHandle  for PipeWriter { fn as_raw_handle(&self) -> RawHandle { self.0.as_raw_handle() } }
+impl AsRawHandle  for PipeReader { fn as_raw_handle(&self) -> RawHandle { self.0.as_raw_handle() } }
+impl IntoRawHandle for PipeWriter { fn into_raw_handle(self) -> RawHandle { self.0.into_raw_handle() } }
+impl IntoRawHandle for PipeReader { fn into_raw_handle(self) -> RawHandle { self.0.into_raw_handle() } }
+
+
+
+/// The read end of a **STDIN** pipe, fed by a background thread
+pub(crate) struct PipeFeeder {
+    read:   Stdio,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PipeFeeder {
+    pub(crate) fn new(bytes: Vec<u8>) -> io::Result<Self> {
+        let (read, mut write) = stdin_pipe()?;
+        let thread = std::thread::Builder::new().name("wslapi pipe feeder".into()).spawn(move || {
+            // Errors (e.g. ERROR_NO_DATA / ERROR_BROKEN_PIPE if the child process exits without reading everything) are
+            // ignored: there's nobody left to read the rest of `bytes` anyways.
+            let _ = write.write_all(&bytes);
+        })?;
+        Ok(Self { read, thread: Some(thread) })
+    }
+}
+
+impl AsRawHandle for PipeFeeder {
+    fn as_raw_handle(&self) -> RawHandle { self.read.as_raw_handle() }
+}
+
+impl Drop for PipeFeeder {
+    fn drop(&mut self) {
+        // Close our read end first: otherwise the feeder thread could block forever on a full pipe
+        self.read = Stdio::null();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+
+
+/// Create a pipe for a child's **STDIN**: (inheritable child read end, non-inheritable parent write end)
+pub(crate) fn stdin_pipe() -> io::Result<(Stdio, PipeWriter)> {
+    let (read, write) = create_pipe()?;
+    let child = unsafe { Stdio::from_handle(read) };
+    let parent = unsafe { PipeWriter(File::from_raw_handle(write.cast())) };
+    disinherit(parent.as_raw_handle())?;
+    Ok((child, parent))
+}
+
+/// Create a pipe for a child's **STDOUT** or **STDERR**: (inheritable child write end, non-inheritable parent read end)
+pub(crate) fn output_pipe() -> io::Result<(Stdio, PipeReader)> {
+    let (read, write) = create_pipe()?;
+    let child = unsafe { Stdio::from_handle(write) };
+    let parent = unsafe { PipeReader(File::from_raw_handle(read.cast())) };
+    disinherit(parent.as_raw_handle())?;
+    Ok((child, parent))
+}
+
+/// Create an anonymous pipe with both ends inheritable: (read, write)
+fn create_pipe() -> io::Result<(HANDLE, HANDLE)> {
+    let mut sa = SECURITY_ATTRIBUTES {
+        nLength:                std::mem::size_of::<SECURITY_ATTRIBUTES>().try_into().unwrap(),
+        lpSecurityDescriptor:   null_mut(),
+        bInheritHandle:         TRUE,
+    };
+    let mut read  = null_mut();
+    let mut write = null_mut();
+    let success = unsafe { CreatePipe(&mut read, &mut write, &mut sa, 0) };
+    if success == FALSE { return Err(io::Error::last_os_error()) }
+    Ok((read, write))
+}
+
+fn disinherit(handle: RawHandle) -> io::Result<()> {
+    let success = unsafe { SetHandleInformation(handle.cast(), HANDLE_FLAG_INHERIT, 0) };
+    if success == FALSE { return Err(io::Error::last_os_error()) }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+//! The commonly needed types, for glob importing:
+//!
+//! ```rust
+//! use wslapi::prelude::*;
+//!
+//! fn default_flags(wsl: &Library, distro: &str) -> Result<WSL_DISTRIBUTION_FLAGS> {
+//!     Ok(wsl.get_distribution_configuration(distro)?.flags)
+//! }
+//! ```
+
+pub use crate::{Configuration, Error, ExitStatus, Library, Process, Result, Stdio, WSL_DISTRIBUTION_FLAGS};
@@ -1,44 +1,264 @@
-use crate::Stdio;
+use crate::{Encoding, PipeReader, PipeWriter, Stdio};
 
-use winapi::shared::minwindef::DWORD;
+use winapi::shared::minwindef::{DWORD, FALSE};
 use winapi::shared::ntdef::HANDLE;
+use winapi::shared::winerror::WAIT_TIMEOUT;
 use winapi::um::handleapi::CloseHandle;
-use winapi::um::processthreadsapi::GetExitCodeProcess;
-use winapi::um::synchapi::WaitForSingleObject;
-use winapi::um::winbase::{INFINITE, WAIT_OBJECT_0};
+use winapi::um::processthreadsapi::{GetExitCodeProcess, GetProcessId, TerminateProcess};
+use winapi::um::synchapi::{WaitForMultipleObjects, WaitForSingleObject};
+use winapi::um::winbase::{INFINITE, WAIT_ABANDONED, WAIT_FAILED, WAIT_OBJECT_0};
 
-use std::io;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, BufReader};
+use std::os::windows::io::{AsRawHandle, BorrowedHandle, RawHandle};
 use std::ptr::null_mut;
+use std::time::{Duration, Instant};
 
 
 
 /// A [WslLaunch]ed Process
 ///
+/// If launched with [StdioKind::Piped] streams, the parent's ends of those pipes are available via
+/// [stdin_mut](#method.stdin_mut), [stdout_mut](#method.stdout_mut), and [stderr_mut](#method.stderr_mut)
+/// (or the `take_*` equivalents), similar to [std::process::Child].
+///
 /// ### See Also
 ///
 /// - [Process]
 /// - [Library::launch]
+/// - [Library::launch_with]
 /// - [WslLaunch]
 ///
 /// [Library::launch]:              struct.Library.html#method.launch
+/// [Library::launch_with]:         struct.Library.html#method.launch_with
+/// [StdioKind::Piped]:             crate::StdioKind::Piped
 /// [WslLaunch]:                    https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
 pub struct Process {
-    pub(crate) handle: HANDLE,
-    pub(crate) stdin:  Stdio,
-    pub(crate) stdout: Stdio,
-    pub(crate) stderr: Stdio,
+    pub(crate) handle:      HANDLE,
+    pub(crate) stdin:       Stdio,
+    pub(crate) stdout:      Stdio,
+    pub(crate) stderr:      Stdio,
+    pub(crate) stdin_pipe:  Option<PipeWriter>,
+    pub(crate) stdout_pipe: Option<PipeReader>,
+    pub(crate) stderr_pipe: Option<PipeReader>,
+    pub(crate) status:      Option<ExitStatus>,
+    pub(crate) started:     Instant,
 }
 
 impl Process {
     /// Waits for the WSL process to exit completely.
+    ///
+    /// Like [std::process::Child::wait], the **STDIN** pipe (if any) is closed first, to avoid deadlocking a process
+    /// that's waiting for EOF.  Any **STDOUT** / **STDERR** pipes are closed too, so [take](#method.take_stdout) them
+    /// first if you still want to read what was written.
     pub fn wait(mut self) -> io::Result<ExitStatus> { self.join_impl() }
 
+    /// The **STDIN** pipe, if launched with [StdioKind::Piped] and not yet [taken](#method.take_stdin).
+    /// `None` for [Stdio::null], files, etc.
+    ///
+    /// <span style="color: red">**Beware deadlocks:**</span>  pipe buffers are finite.  A child process blocked
+    /// writing to a full **STDOUT** pipe won't read more **STDIN**, so writing lots of **STDIN** without concurrently
+    /// reading **STDOUT** / **STDERR** (e.g. from another thread) can hang both processes.
+    ///
+    /// [StdioKind::Piped]:         crate::StdioKind::Piped
+    pub fn stdin_mut(&mut self) -> Option<&mut PipeWriter> { self.stdin_pipe.as_mut() }
+
+    /// The **STDOUT** pipe, if launched with [StdioKind::Piped] and not yet [taken](#method.take_stdout).
+    /// `None` for [Stdio::null], files, etc.  See [stdin_mut](#method.stdin_mut) regarding deadlocks.
+    ///
+    /// [StdioKind::Piped]:         crate::StdioKind::Piped
+    pub fn stdout_mut(&mut self) -> Option<&mut PipeReader> { self.stdout_pipe.as_mut() }
+
+    /// The **STDERR** pipe, if launched with [StdioKind::Piped] and not yet [taken](#method.take_stderr).
+    /// `None` for [Stdio::null], files, etc.  See [stdin_mut](#method.stdin_mut) regarding deadlocks.
+    ///
+    /// [StdioKind::Piped]:         crate::StdioKind::Piped
+    pub fn stderr_mut(&mut self) -> Option<&mut PipeReader> { self.stderr_pipe.as_mut() }
+
+    /// Take ownership of the **STDIN** pipe (e.g. to write from another thread, or to drop it to signal EOF.)
+    pub fn take_stdin(&mut self) -> Option<PipeWriter> { self.stdin_pipe.take() }
+
+    /// Take ownership of the **STDOUT** pipe (e.g. to read from another thread, or after [wait](#method.wait)ing.)
+    pub fn take_stdout(&mut self) -> Option<PipeReader> { self.stdout_pipe.take() }
+
+    /// Take ownership of the **STDERR** pipe (e.g. to read from another thread, or after [wait](#method.wait)ing.)
+    pub fn take_stderr(&mut self) -> Option<PipeReader> { self.stderr_pipe.take() }
+
+    /// The [Stdio] passed to [WslLaunch] as the child's **STDIN** (e.g. a file), for retrieving its raw handle.
+    ///
+    /// This is the *child's* end: if **STDIN** was [StdioKind::Piped], the parent's end is
+    /// [stdin_mut](#method.stdin_mut) instead, and this is [Stdio::null].  It's also [Stdio::null] once the process has been [wait](#method.wait)ed on.
+    ///
+    /// [StdioKind::Piped]:         crate::StdioKind::Piped
+    /// [WslLaunch]:                https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn stdin(&self) -> &Stdio { &self.stdin }
+
+    /// The [Stdio] passed to [WslLaunch] as the child's **STDOUT**.  See [stdin](#method.stdin).
+    ///
+    /// [WslLaunch]:                https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn stdout(&self) -> &Stdio { &self.stdout }
+
+    /// The [Stdio] passed to [WslLaunch] as the child's **STDERR**.  See [stdin](#method.stdin).
+    ///
+    /// [WslLaunch]:                https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn stderr(&self) -> &Stdio { &self.stderr }
+
+    /// Iterate over the lines written to the **STDOUT** pipe, as they arrive (with `\n` / `\r\n` stripped.)
+    ///
+    /// Only meaningful if **STDOUT** was [StdioKind::Piped] and hasn't been
+    /// [taken](#method.take_stdout) - otherwise this yields a single `Err(...)` of kind [io::ErrorKind::NotConnected].
+    /// The iterator buffers internally, so data read past the last yielded line is lost if it's dropped early.
+    /// Iteration ends on EOF, typically once the process exits.  Beware deadlocks with **STDERR** - see [stdin_mut](#method.stdin_mut).
+    ///
+    /// [StdioKind::Piped]:         crate::StdioKind::Piped
+    pub fn stdout_lines(&mut self) -> impl Iterator<Item = io::Result<String>> + '_ {
+        let missing = match self.stdout_pipe {
+            None    => Some(Err(io::Error::new(io::ErrorKind::NotConnected, "stdout_lines(): STDOUT isn't piped"))),
+            Some(_) => None,
+        };
+        missing.into_iter().chain(self.stdout_pipe.as_mut().map(|pipe| BufReader::new(pipe).lines()).into_iter().flatten())
+    }
+
+    /// Waits up to `timeout` for the WSL process to exit completely.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(io::Error)`      - if waiting failed
+    /// - `Ok(None)`            - if `timeout` elapsed before the process exited.  The process may be waited on again, or [kill](#method.kill)ed.
+    /// - `Ok(Some(status))`    - if the process exited (or had already exited)
+    ///
+    /// Timeouts are rounded up to the nearest millisecond, and clamped to `u32::MAX - 1` milliseconds (~49.7 days.)
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+        let ms = (timeout.as_nanos() + 999_999) / 1_000_000;
+        let ms = if ms >= u128::from(INFINITE) { INFINITE - 1 } else { ms as DWORD };
+        self.wait_impl(ms)
+    }
+
+    /// Checks if the WSL process has exited, without blocking.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(io::Error)`      - if waiting failed
+    /// - `Ok(None)`            - if the process is still running.  The process (and its stdio) remain owned by `self`.
+    /// - `Ok(Some(status))`    - if the process exited (or had already exited)
+    ///
+    /// Like [std::process::Child::try_wait], this doesn't close **STDIN**: a process waiting on it will keep running.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> { self.wait_impl(0) }
+
+    /// Waits for the WSL process to exit, *or* for `cancel` (e.g. an event signaled by a GUI's cancel button) to become signaled.
+    ///
+    /// Takes `&mut self` rather than `self`, so a canceled process is left running, and may be waited on again or
+    /// [kill](#method.kill)ed.  Unlike [wait](#method.wait), this doesn't close **STDIN**.
+    ///
+    /// ### Returns
+    ///
+    /// - `Err(io::Error)`      - if waiting failed (e.g. `cancel` isn't a waitable handle)
+    /// - `Ok(None)`            - if `cancel` was signaled first
+    /// - `Ok(Some(status))`    - if the process exited (or had already exited)
+    ///
+    /// ### See Also
+    ///
+    /// - [WaitForMultipleObjects] - the underlying API
+    ///
+    /// [WaitForMultipleObjects]:   https://docs.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-waitformultipleobjects
+    pub fn wait_with_cancel(&mut self, cancel: BorrowedHandle<'_>) -> io::Result<Option<ExitStatus>> {
+        if self.handle.is_null() { return Ok(self.status) } // already waited
+
+        const CANCELED : DWORD = WAIT_OBJECT_0 + 1;
+        let handles = [self.handle, cancel.as_raw_handle().cast()];
+        let wait = unsafe { WaitForMultipleObjects(2, handles.as_ptr(), FALSE, INFINITE) };
+        match wait {
+            WAIT_OBJECT_0   => self.wait_impl(0),
+            CANCELED        => Ok(None),
+            WAIT_FAILED     => Err(std::io::Error::last_os_error()),
+            other           => Err(io::Error::new(io::ErrorKind::Other, format!("WaitForMultipleObjects([process, cancel], ...) returned unexpected value 0x{:08x}", other))),
+        }
+    }
+
+    /// Forcibly terminates the WSL process with an exit code of `1`.
+    ///
+    /// This is a no-op if the process has already exited.  [Process::wait] may still be called afterwards.
+    pub fn kill(&mut self) -> io::Result<()> { self.terminate(1) }
+
+    /// Forcibly terminates the WSL process with an exit code of `exit_code`.
+    ///
+    /// This is a no-op if the process has already exited.  [Process::wait] may still be called afterwards.
+    ///
+    /// ### See Also
+    ///
+    /// - [TerminateProcess] - the underlying API
+    ///
+    /// [TerminateProcess]:         https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-terminateprocess
+    pub fn terminate(&mut self, exit_code: DWORD) -> io::Result<()> {
+        if self.handle.is_null() || self.has_exited() { return Ok(()) }
+
+        let succeeded = unsafe { TerminateProcess(self.handle, exit_code) };
+        if succeeded == 0 {
+            let err = std::io::Error::last_os_error();
+            if self.has_exited() { return Ok(()) } // raced with the process exiting on its own
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Closes the process handle (and the parent's copies of its stdio handles) *without* waiting for it to exit.
+    ///
+    /// Unlike dropping a [Process], this doesn't block: the WSL process keeps running in the background, and its
+    /// exit status can no longer be retrieved.  Useful for fire-and-forget background services.
+    pub fn detach(mut self) -> io::Result<()> {
+        let handle = std::mem::replace(&mut self.handle, null_mut());
+        if handle.is_null() { return Ok(()) } // already waited
+
+        let succeeded = unsafe { CloseHandle(handle) };
+        if succeeded == 0 { return Err(std::io::Error::last_os_error()); }
+        Ok(())
+    }
+
+    /// Returns the Windows process ID of the launched process, or `None` if it's already been waited on.
+    ///
+    /// ### See Also
+    ///
+    /// - [GetProcessId] - the underlying API
+    ///
+    /// [GetProcessId]:             https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getprocessid
+    pub fn id(&self) -> Option<u32> {
+        if self.handle.is_null() { return None }
+        let id = unsafe { GetProcessId(self.handle) };
+        if id == 0 { None } else { Some(id) }
+    }
+
+    /// Get an I/O-safe std::os::windows::io::[BorrowedHandle] to the process, or `None` if it's already been waited on.
+    ///
+    /// [BorrowedHandle]:           https://doc.rust-lang.org/std/os/windows/io/struct.BorrowedHandle.html
+    pub fn as_handle(&self) -> Option<BorrowedHandle<'_>> {
+        if self.handle.is_null() { None } else { Some(unsafe { BorrowedHandle::borrow_raw(self.handle.cast()) }) }
+    }
+
+    /// Get the raw process handle, or `NULL` if it's already been waited on.
+    pub fn as_raw_handle(&self) -> RawHandle { self.handle.cast() }
+
+    fn has_exited(&self) -> bool {
+        unsafe { WaitForSingleObject(self.handle, 0) == WAIT_OBJECT_0 }
+    }
+
     // Also called by `Drop`
     fn join_impl(&mut self) -> io::Result<ExitStatus> {
-        assert!(!self.handle.is_null(), "Process::join_impl already called once");
+        self.stdin_pipe = None; // EOF, lest the process wait on us forever
+        Ok(self.wait_impl(INFINITE)?.expect("WaitForSingleObject(..., INFINITE) timed out"))
+    }
 
-        let wait = unsafe { WaitForSingleObject(self.handle, INFINITE) };
-        if wait != WAIT_OBJECT_0 { return Err(std::io::Error::last_os_error()); }
+    pub(crate) fn wait_impl(&mut self, timeout_ms: DWORD) -> io::Result<Option<ExitStatus>> {
+        if self.handle.is_null() { return Ok(self.status) } // already waited
+
+        let wait = unsafe { WaitForSingleObject(self.handle, timeout_ms) };
+        match wait {
+            WAIT_OBJECT_0   => {},
+            WAIT_TIMEOUT    => return Ok(None),
+            // Only WAIT_FAILED sets GetLastError - otherwise we'd report "The operation completed successfully."
+            WAIT_FAILED     => return Err(std::io::Error::last_os_error()),
+            WAIT_ABANDONED  => return Err(io::Error::new(io::ErrorKind::Other, "WaitForSingleObject(process, ...) returned WAIT_ABANDONED")),
+            other           => return Err(io::Error::new(io::ErrorKind::Other, format!("WaitForSingleObject(process, ...) returned unexpected value 0x{:08x}", other))),
+        }
 
         let handle  = std::mem::replace(&mut self.handle, null_mut());
         let _stdin  = std::mem::replace(&mut self.stdin,  Stdio::null());
@@ -48,37 +268,166 @@ impl Process {
         let mut exit_code = 0;
         let succeeded = unsafe { GetExitCodeProcess(handle, &mut exit_code) };
         let exit_code = if succeeded != 0 { Some(exit_code) } else { None };
+        self.status = Some(ExitStatus { exit_code, elapsed: Some(self.started.elapsed()) });
 
         let succeeded = unsafe { CloseHandle(handle) };
         if succeeded == 0 { return Err(std::io::Error::last_os_error()); }
 
-        Ok(ExitStatus { exit_code })
+        Ok(self.status)
     }
 }
 
 impl std::ops::Drop for Process {
     fn drop(&mut self) {
-        if !self.handle.is_null() {
-            self.join_impl().expect("winapi error while dropping wslapi::Process");
+        // Close our output pipes too: a process blocked writing to a full, unread pipe would otherwise never exit.
+        self.stdout_pipe = None;
+        self.stderr_pipe = None;
+        if !self.handle.is_null() && self.join_impl().is_err() {
+            // Panicking in a destructor (possibly while already unwinding) would risk an abort, so swallow the error,
+            // but still close the handle instead of leaking it.
+            let handle = std::mem::replace(&mut self.handle, null_mut());
+            if !handle.is_null() { unsafe { CloseHandle(handle) }; }
         }
     }
 }
 
 
 
+/// A [Library::launch_piped]ed Process, and the parent's ends of its stdio pipes
+///
+/// ### See Also
+///
+/// - [Process]
+/// - [Library::launch_piped]
+///
+/// [Library::launch_piped]:        struct.Library.html#method.launch_piped
+pub struct PipedProcess {
+    /// The WSL process itself
+    pub process:    Process,
+
+    /// Write to the process's **STDIN**.  Drop this to close the pipe (EOF).
+    pub stdin:      PipeWriter,
+
+    /// Read from the process's **STDOUT**.
+    pub stdout:     PipeReader,
+
+    /// Read from the process's **STDERR**.
+    pub stderr:     PipeReader,
+}
+
+
+
+/// The collected output of a finished WSL process.
+///
+/// ### See Also
+///
+/// - [Library::output]
+///
+/// [Library::output]:              struct.Library.html#method.output
+#[derive(Clone, Debug)]
+pub struct Output {
+    /// The exit status of the process.
+    pub status: ExitStatus,
+
+    /// Everything the process wrote to **STDOUT**, as raw bytes (unaffected by the Windows console code page.)
+    pub stdout: Vec<u8>,
+
+    /// Everything the process wrote to **STDERR**, as raw bytes (unaffected by the Windows console code page.)
+    pub stderr: Vec<u8>,
+}
+
+impl Output {
+    /// Decode [stdout](#structfield.stdout) as `encoding`, replacing invalid sequences with `U+FFFD`.
+    pub fn stdout_decoded(&self, encoding: Encoding) -> String { encoding.decode(&self.stdout) }
+
+    /// Decode [stderr](#structfield.stderr) as `encoding`, replacing invalid sequences with `U+FFFD`.
+    pub fn stderr_decoded(&self, encoding: Encoding) -> String { encoding.decode(&self.stderr) }
+}
+
+
+
 /// The exit status of a WSL process.
+///
+/// An exit status is in one of three states:
+///
+/// - **success** - [success](#method.success) (exit code `0`)
+/// - **known failure** - a nonzero [code](#method.code), e.g. `Some(1)` for `exit 1`
+/// - **unknown** - [is_unknown](#method.is_unknown): either `GetExitCodeProcess` failed ([code](#method.code) is `None`),
+///   or WSL itself couldn't determine the exit code, and reported `!0` / `0xFFFFFFFF` instead.  Note that [code](#method.code)
+///   is `Some(!0)` in the latter case.
+#[derive(Clone, Copy, Debug)]
 pub struct ExitStatus {
     exit_code:  Option<DWORD>,
+    elapsed:    Option<Duration>,
 }
 
 impl ExitStatus {
+    #[cfg_attr(not(feature = "mock"), allow(dead_code))]
+    pub(crate) fn from_code(exit_code: DWORD) -> Self { Self { exit_code: Some(exit_code), elapsed: None } }
+
     /// Was termination successful?
     pub fn success(&self) -> bool { self.exit_code == Some(0) }
 
     /// Returns the exit code of the process, if any.
+    ///
+    /// This is the raw, untruncated `DWORD` reported by Windows for the WSL process.  Depending on the distro and
+    /// WSL version, `exit 0x101` may be reported as either `0x101` or (POSIX truncated) `1`, and `!0` may be reported
+    /// if WSL couldn't determine the exit code at all.  See [code_posix](#method.code_posix) for a truncated code.
     pub fn code(&self) -> Option<DWORD> { self.exit_code }
     // While POSIX truncates the result to 1 byte / 8 bits / 0xFF, it's possible
     // that the WSL process itself could crash/fail/kernel panic/??? with other
     // exit code results.  As such, I retain the API.  Unlike std::process::ExitCode,
     // the mapped code in question is *unsigned*.
+
+    /// Returns `true` if the exit code is unknown: either it couldn't be retrieved at all, or WSL reported `!0`.
+    pub fn is_unknown(&self) -> bool { self.exit_code.map_or(true, |code| code == !0) }
+
+    /// Returns the exit code of the process, truncated to 8 bits (`code & 0xFF`) as a POSIX shell would see it.
+    ///
+    /// This gives consistent results regardless of whether WSL reported the full or truncated exit code, but note
+    /// that an unknown exit code (`!0`) will be reported as `255`.
+    pub fn code_posix(&self) -> Option<u8> { self.exit_code.map(|code| (code & 0xFF) as u8) }
+
+    /// Returns the wall time between launching the process and observing its exit via [Process::wait] (or similar.)
+    ///
+    /// This includes [WslLaunch] overhead such as starting the distribution or WSL 2 VM, and is only as precise as the
+    /// wait: [Process::try_wait] only observes the exit when polled.  `Duration::ZERO` if unknown (e.g. mock results.)
+    ///
+    /// [WslLaunch]:    https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn duration(&self) -> Duration { self.elapsed.unwrap_or(Duration::ZERO) }
+
+    /// Convert into a [std::process::ExitStatus] with the same raw (untruncated) exit code, if any.
+    pub fn into_std(self) -> Option<std::process::ExitStatus> {
+        use std::os::windows::process::ExitStatusExt;
+        self.exit_code.map(std::process::ExitStatus::from_raw)
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.exit_code {
+            Some(code)  => write!(fmt, "exit code: {}", code),
+            None        => write!(fmt, "exit code: unknown"),
+        }
+    }
+}
+
+#[test] fn exit_status() {
+    let status = |exit_code| ExitStatus { exit_code: Some(exit_code), elapsed: None };
+    assert!( status(0).success());
+    assert!(!status(0x100).success());
+    assert_eq!(status(0x101).code(),        Some(0x101));
+    assert_eq!(status(0x101).code_posix(),  Some(1));
+    assert_eq!(status(0x100).code_posix(),  Some(0));
+    assert_eq!(status(!0).code_posix(),     Some(0xFF));
+    assert_eq!(status(0x101).into_std().and_then(|s| s.code()), Some(0x101));
+    assert_eq!(ExitStatus { exit_code: None, elapsed: None }.code_posix(), None);
+    assert!(!status(0).is_unknown());
+    assert!(!status(0xFF).is_unknown());
+    assert!( status(!0).is_unknown());
+    assert!( ExitStatus { exit_code: None, elapsed: None }.is_unknown());
+    assert_eq!(status(3).to_string(), "exit code: 3");
+    assert_eq!(ExitStatus { exit_code: None, elapsed: None }.to_string(), "exit code: unknown");
+    assert_eq!(status(0).duration(), Duration::ZERO);
+    assert_eq!(ExitStatus { exit_code: Some(0), elapsed: Some(Duration::from_millis(5)) }.duration(), Duration::from_millis(5));
 }
@@ -2,14 +2,20 @@
 
 #![deny(unreachable_patterns)]
 
-use winapi::shared::minwindef::{DWORD, HKEY};
+use crate::WSL_DISTRIBUTION_FLAGS;
+
+use winapi::shared::minwindef::{DWORD, HKEY, MAX_PATH};
 use winapi::shared::winerror::*;
+use winapi::um::processenv::ExpandEnvironmentStringsW;
 use winapi::um::winbase::{FormatMessageW, FORMAT_MESSAGE_FROM_SYSTEM};
-use winapi::um::winnt::KEY_ENUMERATE_SUB_KEYS;
+use winapi::um::winnt::{KEY_ENUMERATE_SUB_KEYS, KEY_QUERY_VALUE, REG_SZ};
 use winapi::um::winreg::*;
 
+use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::PathBuf;
 use std::ptr::null_mut;
 use std::os::windows::prelude::*;
 
@@ -18,12 +24,16 @@ use std::os::windows::prelude::*;
 /// Get the `DistributionName`s of all registered WSL distributions from
 /// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{...}\DistributionName`
 ///
+/// Registry errors (e.g. access denied, or a `{...}` subkey missing its `DistributionName`) are yielded as `Err(...)`
+/// rather than panicking.  An error opening or enumerating the `Lxss` key itself ends iteration.
+///
 /// # Example
 ///
 /// ```rust
 /// let library = wslapi::Library::new();
 ///
 /// for distro in wslapi::registry::distribution_names() {
+///     let distro = distro.unwrap();
 ///     let library = library.as_ref().unwrap_or_else(|err| panic!(
 ///         "WSL not available despite having WSL distributions: {}", err
 ///     ));
@@ -34,28 +44,336 @@ use std::os::windows::prelude::*;
 ///     );
 /// }
 /// ```
-pub fn distribution_names() -> impl Iterator<Item = OsString> { DistributionNames::new() }
+pub fn distribution_names() -> impl Iterator<Item = io::Result<OsString>> { LxssSubkeys::new().map(|r| r.map(|(_guid, name)| name)) }
+
+/// Get the `DistributionName`s of all WSL distributions registered under either
+/// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{...}\DistributionName` or
+/// `HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{...}\DistributionName`
+///
+/// Some deployments register distributions machine-wide, which [distribution_names] won't see - notably when running
+/// as `SYSTEM`.  Per-user distributions are yielded first, and a `{...}` GUID present in both hives is only yielded once.
+/// Registry errors are yielded as `Err(...)` rather than panicking, as per [distribution_names].
+///
+/// Note that other `registry::*` functions, and `wslapi.dll` itself, only consider the current user's distributions.
+///
+/// # Example
+///
+/// ```rust
+/// let all = wslapi::registry::distribution_names_all().collect::<Result<Vec<_>, _>>().unwrap();
+/// for distro in wslapi::registry::distribution_names() {
+///     let distro = distro.unwrap();
+///     assert!(all.contains(&distro), "missing from distribution_names_all: {}", distro.to_string_lossy());
+/// }
+/// ```
+pub fn distribution_names_all() -> impl Iterator<Item = io::Result<OsString>> {
+    let mut guids = HashSet::new();
+    LxssSubkeys::new().chain(LxssSubkeys::open(HKEY_LOCAL_MACHINE, "HKEY_LOCAL_MACHINE"))
+        .filter(move |r| match r {
+            Ok((guid, _name))   => guids.insert(guid.to_string_lossy().to_ascii_uppercase()), // registry keys are case insensitive
+            Err(_)              => true,
+        })
+        .map(|r| r.map(|(_guid, name)| name))
+}
+
+/// Check if WSL appears to be installed, by checking for the presence of
+/// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss`
+///
+/// This is cheaper than [Library::new] and won't fail noisily, making it a good gate before heavier operations.
+/// Note that the key is created when WSL is first used by the current user, so this may return `false` on a machine
+/// with WSL freshly installed but never used.
+///
+/// [Library::new]:     crate::Library::new
+///
+/// # Example
+///
+/// ```rust
+/// if !wslapi::registry::is_wsl_installed() {
+///     assert_eq!(wslapi::registry::distribution_names().count(), 0);
+/// }
+/// ```
+pub fn is_wsl_installed() -> bool {
+    let mut result = null_mut();
+    let path = wchar::wch_c!(r"SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss");
+    let status = unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, path.as_ptr(), 0, KEY_QUERY_VALUE, &mut result) };
+    if status as DWORD != ERROR_SUCCESS { return false }
+    let status = unsafe { RegCloseKey(result) };
+    assert_eq!(ERROR_SUCCESS, status as _, "RegCloseKey(lxss) failed with error 0x{:04x})", status);
+    true
+}
+
+/// Get the [DistributionInfo] of all registered WSL distributions from
+/// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{...}`
+///
+/// This reads the registry directly, instead of going through `wslapi.dll` per distribution.
+/// Registry errors are yielded as `Err(...)` rather than panicking, as per [distribution_names].
+///
+/// # Example
+///
+/// ```rust
+/// for distro in wslapi::registry::distributions() {
+///     let distro = distro.unwrap();
+///     assert!((1..=2).contains(&distro.version), "{:?}", distro);
+///     assert!(distro.guid.starts_with('{') && distro.guid.ends_with('}'), "{:?}", distro);
+/// }
+/// ```
+pub fn distributions() -> impl Iterator<Item = io::Result<DistributionInfo>> {
+    LxssSubkeys::new().map(|r| r.and_then(|(guid, name)| DistributionInfo::read(guid, name)))
+}
+
+/// Get the `DistributionName` of the default WSL distribution (the one a bare `wsl` invocation targets) from
+/// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{DefaultDistribution}\DistributionName`
+///
+/// Returns `Ok(None)` if there is no default distribution (e.g. no distributions are installed.)
+///
+/// # Example
+///
+/// ```rust
+/// if let Some(distro) = wslapi::registry::default_distribution_name().unwrap() {
+///     assert!(
+///         wslapi::registry::distribution_names().filter_map(Result::ok).any(|d| d == distro),
+///         "default distribution not enumerated: {}",
+///         distro.to_string_lossy()
+///     );
+/// }
+/// ```
+pub fn default_distribution_name() -> io::Result<Option<OsString>> {
+    let guid = match get_string_value(OsStr::new(LXSS), "DefaultDistribution")? { Some(guid) => guid, None => return Ok(None) };
+    get_string_value(&lxss_subkey(&guid), "DistributionName")
+}
+
+/// Get the WSL version (`1` or `2`) used for newly registered/imported distributions from
+/// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\DefaultVersion`
+///
+/// Returns `Ok(None)` if the value isn't set (e.g. `wsl --set-default-version` was never used.)
+/// Per-distribution versions can be retrieved via [Configuration::version] or [DistributionInfo::version].
+///
+/// [Configuration::version]:   crate::Configuration::version
+///
+/// # Example
+///
+/// ```rust
+/// if let Some(version) = wslapi::registry::default_version().unwrap() {
+///     assert!((1..=2).contains(&version), "unexpected default WSL version {}", version);
+/// }
+/// ```
+pub fn default_version() -> io::Result<Option<u32>> {
+    get_dword_value(OsStr::new(LXSS), "DefaultVersion")
+}
+
+/// Get the `BasePath` of a registered WSL distribution from
+/// `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{...}\BasePath`,
+/// expanding any environment variables (e.g. `%LOCALAPPDATA%`) it contains.
+///
+/// This is the directory containing the distribution's `rootfs` (WSL1) or `ext4.vhdx` (WSL2).
+///
+/// Returns `Ok(None)` if no distribution named `name` was found, or it had no `BasePath`.
+///
+/// # Example
+///
+/// ```rust
+/// for distro in wslapi::registry::distribution_names() {
+///     let distro = distro.unwrap();
+///     if let Some(path) = wslapi::registry::distribution_base_path(&distro).unwrap() {
+///         println!("{}: {}", distro.to_string_lossy(), path.display());
+///     }
+/// }
+/// assert!(wslapi::registry::distribution_base_path("Nonexistant").unwrap().is_none());
+/// ```
+pub fn distribution_base_path(name: impl AsRef<OsStr>) -> io::Result<Option<PathBuf>> {
+    let guid = match find_guid(name.as_ref())? { Some(guid) => guid, None => return Ok(None) };
+    let path = get_string_value(&lxss_subkey(&guid), "BasePath")?;
+    Ok(path.map(|path| PathBuf::from(expand_environment_strings(&path))))
+}
+
+/// Get the `{...}` GUID subkey name (under `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss`) of the distribution named `name`.
+///
+/// Unlike the friendly name, which can change (e.g. via `wsl.exe --import` of the same name after unregistering), the
+/// GUID is a stable identifier for a particular registration.
+///
+/// Returns `Ok(None)` if no distribution named `name` was found.
+///
+/// # Example
+///
+/// ```rust
+/// for distro in wslapi::registry::distribution_names() {
+///     let distro = distro.unwrap();
+///     let guid = wslapi::registry::distribution_guid(&distro).unwrap().unwrap();
+///     assert!(guid.starts_with('{') && guid.ends_with('}'), "{}: {}", distro.to_string_lossy(), guid);
+/// }
+/// assert!(wslapi::registry::distribution_guid("Nonexistant").unwrap().is_none());
+/// ```
+pub fn distribution_guid(name: impl AsRef<OsStr>) -> io::Result<Option<String>> {
+    Ok(find_guid(name.as_ref())?.map(|guid| guid.to_string_lossy().into_owned()))
+}
+
 
 
+/// A registered WSL distribution, as described by `HKCU\SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss\{...}`
+///
+/// Missing registry values are replaced with defaults rather than treated as errors.
+#[derive(Clone, Debug)]
+pub struct DistributionInfo {
+    /// The `{...}` subkey name uniquely identifying this distribution.
+    pub guid:           String,
+
+    /// The `DistributionName` (for example, "Ubuntu-20.04".)
+    pub name:           OsString,
+
+    /// The `BasePath` containing the distribution's file system, with environment variables expanded (or empty if missing.)
+    pub base_path:      PathBuf,
+
+    /// The `Version` of WSL for which this distribution is configured (or `1` if missing.)
+    pub version:        u32,
+
+    /// The `DefaultUid` used when launching new WSL sessions for this distribution (or `0` / root if missing.)
+    pub default_uid:    u32,
+
+    /// The `Flags` governing the behavior of this distribution (or [WSL_DISTRIBUTION_FLAGS::DEFAULT] if missing.)
+    pub flags:          WSL_DISTRIBUTION_FLAGS,
+}
+
+impl DistributionInfo {
+    fn read(guid: OsString, name: OsString) -> io::Result<Self> {
+        let key = lxss_subkey(&guid);
+        Ok(Self {
+            guid:           guid.to_string_lossy().into_owned(),
+            name,
+            base_path:      get_string_value(&key, "BasePath")?.map_or(PathBuf::new(), |p| PathBuf::from(expand_environment_strings(&p))),
+            version:        get_dword_value(&key, "Version")?.unwrap_or(1),
+            default_uid:    get_dword_value(&key, "DefaultUid")?.unwrap_or(0),
+            flags:          get_dword_value(&key, "Flags")?.map_or(WSL_DISTRIBUTION_FLAGS::DEFAULT, WSL_DISTRIBUTION_FLAGS::from),
+        })
+    }
+}
+
+
+
+pub(crate) const LXSS : &str = r"SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss";
+
+fn lxss_subkey(guid: &OsStr) -> OsString {
+    let mut path = OsString::from(LXSS);
+    path.push("\\");
+    path.push(guid);
+    path
+}
 
-struct DistributionNames {
+pub(crate) fn find_guid(name: &OsStr) -> io::Result<Option<OsString>> {
+    for subkey in LxssSubkeys::new() {
+        let (guid, n) = match subkey {
+            Ok(subkey)                                          => subkey,
+            Err(err) if err.kind() == io::ErrorKind::NotFound   => continue, // some unrelated subkey has no DistributionName
+            Err(err)                                            => return Err(err),
+        };
+        if n == name { return Ok(Some(guid)) }
+    }
+    Ok(None)
+}
+
+fn reg_error(status: DWORD, context: std::fmt::Arguments) -> io::Error {
+    let kind = io::Error::from_raw_os_error(status as i32).kind();
+    io::Error::new(kind, format!("{} failed with error {}", context, format_message(status)))
+}
+
+/// Read a `REG_SZ` value from `HKCU\{subkey}\{value}`, or `None` if the key or value doesn't exist
+fn get_string_value(subkey: &OsStr, value: &str) -> io::Result<Option<OsString>> {
+    let wsubkey = subkey.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let wvalue  = OsStr::new(value).encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    match reg_get_sz(HKEY_CURRENT_USER, wsubkey.as_ptr(), wvalue.as_ptr()) {
+        Ok(value)                   => Ok(Some(value)),
+        Err(ERROR_FILE_NOT_FOUND)   => Ok(None),
+        Err(err)                    => Err(reg_error(err, format_args!("RegGetValueW(HKEY_CURRENT_USER, {:?}, {:?}, ...)", subkey, value))),
+    }
+}
+
+/// [RegGetValueW] a `REG_SZ` value, growing the buffer as necessary for long values.
+///
+/// [RegGetValueW]:     https://docs.microsoft.com/en-us/windows/win32/api/winreg/nf-winreg-reggetvaluew
+fn reg_get_sz(key: HKEY, subkey: *const u16, value: *const u16) -> Result<OsString, DWORD> {
+    let mut data = vec![0u16; 256];
+    loop {
+        let mut data_len : DWORD = (data.len() * 2).try_into().unwrap();
+        let status = unsafe { RegGetValueW(key, subkey, value, RRF_RT_REG_SZ, null_mut(), data.as_mut_ptr().cast(), &mut data_len) };
+        match status as _ {
+            ERROR_SUCCESS   => return Ok(OsString::from_wide(&data[..(usize::try_from(data_len).unwrap()/2).saturating_sub(1)])),
+            ERROR_MORE_DATA => {
+                // data_len is now the required size in bytes... unless the value grew in the meantime, so grow by at least 2x.
+                let required = (usize::try_from(data_len).unwrap() + 1) / 2;
+                let len = required.max(data.len() * 2);
+                data.resize(len, 0);
+            },
+            err             => return Err(err),
+        }
+    }
+}
+
+/// Write a `REG_SZ` value to `HKCU\{subkey}\{value}`, creating or replacing it
+pub(crate) fn set_string_value(subkey: &OsStr, value: &str, data: &OsStr) -> io::Result<()> {
+    let wsubkey = subkey.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let wvalue  = OsStr::new(value).encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let wdata   = data.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let data_len = (wdata.len() * 2).try_into().unwrap();
+    let status = unsafe { RegSetKeyValueW(HKEY_CURRENT_USER, wsubkey.as_ptr(), wvalue.as_ptr(), REG_SZ, wdata.as_ptr().cast(), data_len) };
+    match status as _ {
+        ERROR_SUCCESS   => Ok(()),
+        err             => Err(reg_error(err, format_args!("RegSetKeyValueW(HKEY_CURRENT_USER, {:?}, {:?}, REG_SZ, {:?}, ...)", subkey, value, data))),
+    }
+}
+
+/// Read a `REG_DWORD` value from `HKCU\{subkey}\{value}`, or `None` if the key or value doesn't exist
+fn get_dword_value(subkey: &OsStr, value: &str) -> io::Result<Option<DWORD>> {
+    let wsubkey = subkey.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let wvalue  = OsStr::new(value).encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let mut data : DWORD = 0;
+    let mut data_len = std::mem::size_of::<DWORD>().try_into().unwrap();
+    let status = unsafe { RegGetValueW(HKEY_CURRENT_USER, wsubkey.as_ptr(), wvalue.as_ptr(), RRF_RT_REG_DWORD, null_mut(), (&mut data as *mut DWORD).cast(), &mut data_len) };
+    match status as _ {
+        ERROR_SUCCESS           => Ok(Some(data)),
+        ERROR_FILE_NOT_FOUND    => Ok(None),
+        err                     => Err(reg_error(err, format_args!("RegGetValueW(HKEY_CURRENT_USER, {:?}, {:?}, ...)", subkey, value))),
+    }
+}
+
+fn expand_environment_strings(src: &OsStr) -> OsString {
+    // https://docs.microsoft.com/en-us/windows/win32/api/processenv/nf-processenv-expandenvironmentstringsw
+    let wsrc = src.encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let mut buffer = vec![0u16; wsrc.len().max(MAX_PATH)];
+    loop {
+        let tchars = unsafe { ExpandEnvironmentStringsW(wsrc.as_ptr(), buffer.as_mut_ptr(), buffer.len().try_into().unwrap()) };
+        let tchars = usize::try_from(tchars).unwrap();
+        if tchars == 0 {
+            return src.to_owned(); // failed to expand, return as-is
+        } else if tchars > buffer.len() {
+            buffer.resize(tchars, 0);
+        } else {
+            return OsString::from_wide(&buffer[..tchars-1]);
+        }
+    }
+}
+
+
+
+/// Enumerates (`{GUID}` subkey name, `DistributionName`) pairs
+struct LxssSubkeys {
     lxss:   HKEY,
     index:  DWORD,
+    error:  Option<io::Error>,
 }
 
-impl std::ops::Drop for DistributionNames {
+impl std::ops::Drop for LxssSubkeys {
     fn drop(&mut self) { self.close() }
 }
 
-impl DistributionNames {
-    fn new() -> Self {
+impl LxssSubkeys {
+    fn new() -> Self { Self::open(HKEY_CURRENT_USER, "HKEY_CURRENT_USER") }
+
+    fn open(root: HKEY, root_name: &str) -> Self {
         let mut result = null_mut();
         let path = wchar::wch_c!(r"SOFTWARE\Microsoft\Windows\CurrentVersion\Lxss");
-        let status = unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, path.as_ptr(), 0, KEY_ENUMERATE_SUB_KEYS, &mut result) };
+        let status = unsafe { RegOpenKeyExW(root, path.as_ptr(), 0, KEY_ENUMERATE_SUB_KEYS, &mut result) };
         match status as _ {
-            ERROR_SUCCESS           => Self { lxss: result, index: 0 },
-            ERROR_FILE_NOT_FOUND    => Self { lxss: null_mut(), index: 0 }, // No WSL installed?
-            err                     => panic!("RegOpenKeyExW(HKEY_CURRENT_USER, r\"SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Lxss\", ...) failed with error {}", format_message(err)),
+            ERROR_SUCCESS           => Self { lxss: result, index: 0, error: None },
+            ERROR_FILE_NOT_FOUND    => Self { lxss: null_mut(), index: 0, error: None }, // No WSL installed?
+            err                     => Self { lxss: null_mut(), index: 0, error: Some(reg_error(err, format_args!("RegOpenKeyExW({}, r\"SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Lxss\", ...)", root_name))) },
         }
     }
 
@@ -68,9 +386,10 @@ impl DistributionNames {
     }
 }
 
-impl Iterator for DistributionNames {
-    type Item = OsString;
-    fn next(&mut self) -> Option<OsString> {
+impl Iterator for LxssSubkeys {
+    type Item = io::Result<(OsString, OsString)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() { return Some(Err(err)) }
         if self.lxss.is_null() { return None }
 
         let mut key_name = [0u16; 256]; // https://docs.microsoft.com/en-us/windows/win32/sysinfo/registry-element-size-limits
@@ -79,19 +398,20 @@ impl Iterator for DistributionNames {
         match status as _ {
             ERROR_SUCCESS => {
                 self.index += 1;
-                let mut value = [0u16; 64 * 1024]; // 64 KiB should be enough for a distro name, probably, right?
-                let mut value_len = value.len().try_into().unwrap();
-                let status = unsafe { RegGetValueW(self.lxss, key_name.as_ptr(), wchar::wch_c!("DistributionName").as_ptr(), RRF_RT_REG_SZ, null_mut(), value.as_mut_ptr().cast(), &mut value_len) };
-                match status as _ {
-                    ERROR_SUCCESS   => Some(OsString::from_wide(&value[..(usize::try_from(value_len).unwrap()/2-1)])),
-                    err             => panic!("RegGetValueW(self.lxss, \"{{...}}\", \"DistributionName\", ...) failed with error {}", format_message(err)),
+                let guid = OsString::from_wide(&key_name[..usize::try_from(key_len).unwrap()]);
+                match reg_get_sz(self.lxss, key_name.as_ptr(), wchar::wch_c!("DistributionName").as_ptr()) {
+                    Ok(name)    => Some(Ok((guid, name))),
+                    Err(err)    => Some(Err(reg_error(err, format_args!("RegGetValueW(self.lxss, {:?}, \"DistributionName\", ...)", guid)))),
                 }
             },
             ERROR_NO_MORE_ITEMS => {
                 self.close();
                 None
             },
-            err => panic!("RegEnumKeyExW(self.lxss, ...) failed with error {}", format_message(err)),
+            err => {
+                self.close(); // don't keep retrying the same index forever
+                Some(Err(reg_error(err, format_args!("RegEnumKeyExW(self.lxss, {}, ...)", self.index))))
+            },
         }
     }
 }
@@ -0,0 +1,130 @@
+//! `feature = "serde"`: [Serialize] / [Deserialize] implementations
+//!
+//! - [WSL_DISTRIBUTION_FLAGS] serialize as a `u32`, and deserialize from a `u32`, a `"ENABLE_INTEROP|APPEND_NT_PATH"`
+//!   style string (see [FromStr](std::str::FromStr)), or a list of such strings.
+//! - [EnvironmentVariables] serialize as a map of lossily converted UTF8 strings.  Keys and values that aren't valid
+//!   UTF8 will have invalid sequences replaced with `U+FFFD`, which doesn't round trip.
+//! - [Configuration] serializes as a struct of the above, via `#[derive(...)]`.
+//!
+//! [Configuration]:    crate::Configuration
+
+use crate::{EnvironmentVariables, WSL_DISTRIBUTION_FLAGS};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+
+use std::convert::TryFrom;
+use std::fmt::{self, Formatter};
+
+
+
+impl Serialize for WSL_DISTRIBUTION_FLAGS {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(u32::from(*self))
+    }
+}
+
+impl<'de> Deserialize<'de> for WSL_DISTRIBUTION_FLAGS {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(FlagsVisitor)
+    }
+}
+
+struct FlagsVisitor;
+
+impl<'de> Visitor<'de> for FlagsVisitor {
+    type Value = WSL_DISTRIBUTION_FLAGS;
+
+    fn expecting(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "a u32, a string like \"ENABLE_INTEROP|APPEND_NT_PATH\", or a list of such strings")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        u32::try_from(value).map(WSL_DISTRIBUTION_FLAGS::from).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        u32::try_from(value).map(WSL_DISTRIBUTION_FLAGS::from).map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut flags = WSL_DISTRIBUTION_FLAGS::NONE;
+        while let Some(flag) = seq.next_element::<String>()? {
+            flags |= flag.parse().map_err(de::Error::custom)?;
+        }
+        Ok(flags)
+    }
+}
+
+
+
+impl Serialize for EnvironmentVariables {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (k, v) in self.iter() {
+            map.serialize_entry(&String::from_utf8_lossy(k), &String::from_utf8_lossy(v))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvironmentVariables {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(EnvironmentVariablesVisitor)
+    }
+}
+
+struct EnvironmentVariablesVisitor;
+
+impl<'de> Visitor<'de> for EnvironmentVariablesVisitor {
+    type Value = EnvironmentVariables;
+
+    fn expecting(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "a map of environment variable names to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut pairs = Vec::<(String, String)>::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((k, v)) = map.next_entry::<String, String>()? {
+            if k.contains('=') || k.contains('\0') { return Err(de::Error::custom(format!("invalid environment variable name {:?}", k))); }
+            if v.contains('\0') { return Err(de::Error::custom(format!("environment variable {:?} contained '\\0'", k))); }
+            pairs.push((k, v));
+        }
+        Ok(EnvironmentVariables::from_pairs(pairs.iter().map(|(k, v)| (k.as_bytes(), v.as_bytes()))))
+    }
+}
+
+#[test] fn flags_de() {
+    use serde::de::value::{Error, SeqDeserializer, StrDeserializer, U32Deserializer};
+    use WSL_DISTRIBUTION_FLAGS as F;
+    let from_u32 = |v: u32| F::deserialize(U32Deserializer::<Error>::new(v)).ok();
+    let from_str = |v: &str| F::deserialize(StrDeserializer::<Error>::new(v)).ok();
+    let from_seq = |v: &[&str]| F::deserialize(SeqDeserializer::<_, Error>::new(v.iter().copied())).ok();
+
+    for &bits in [0, 1, 2, 4, 7, 8, 0xF, 0x80000001, !0].iter() { // serialized as u32(bits)
+        assert_eq!(from_u32(u32::from(F::from(bits))), Some(F::from(bits)));
+    }
+    assert_eq!(from_str("ENABLE_INTEROP|0x8"),                    Some(F::from(0x9)));
+    assert_eq!(from_str("WSL_DISTRIBUTION_FLAGS::(DEFAULT|0x8)"), Some(F::from(0xF)));
+    assert_eq!(from_str("ENABLE_INTEROPS"),                       None);
+    assert_eq!(from_str("0x+5"),                                  None);
+    assert_eq!(from_seq(&["ENABLE_INTEROP", "0x8"]),              Some(F::from(0x9)));
+    assert_eq!(from_seq(&[]),                                     Some(F::NONE));
+    assert_eq!(from_seq(&["ENABLE_INTEROP", "BOGUS"]),            None);
+}
+
+#[test] fn environment_variables_de() {
+    use serde::de::value::{Error, MapDeserializer};
+    let de = |pairs: &[(&str, &str)]| EnvironmentVariables::deserialize(MapDeserializer::<_, Error>::new(pairs.iter().copied()));
+
+    let vars = de(&[("PATH", "/usr/bin:/bin"), ("EMPTY", "")]).unwrap();
+    assert_eq!(vars.iter().collect::<Vec<_>>(), [(&b"PATH"[..], &b"/usr/bin:/bin"[..]), (&b"EMPTY"[..], &b""[..])]);
+    assert!(de(&[("A=B", "C")]).is_err());
+    assert!(de(&[("A\0", "C")]).is_err());
+    assert!(de(&[("A", "C\0")]).is_err());
+}
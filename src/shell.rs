@@ -0,0 +1,142 @@
+//! POSIX shell quoting for building WSL command lines
+
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+
+
+/// Quote `arg` for a POSIX shell (`sh`, `bash`, ...), such that it's passed as a single literal argument.
+///
+/// Arguments consisting only of "safe" characters are left as-is.  Everything else is wrapped in `'single quotes'`,
+/// with embedded `'` characters written as `'\''`.
+pub(crate) fn quote(arg: &OsStr) -> OsString {
+    let arg = arg.encode_wide().collect::<Vec<_>>();
+    if !arg.is_empty() && arg.iter().all(|&ch| is_safe(ch)) { return OsString::from_wide(&arg) }
+
+    let mut quoted = Vec::with_capacity(arg.len() + 2);
+    quoted.push(u16::from(b'\''));
+    for ch in arg {
+        if ch == u16::from(b'\'') {
+            quoted.extend(r"'\''".encode_utf16());
+        } else {
+            quoted.push(ch);
+        }
+    }
+    quoted.push(u16::from(b'\''));
+    OsString::from_wide(&quoted)
+}
+
+/// [quote] each argument, and join them with spaces into a single command line.
+pub(crate) fn join(args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> OsString {
+    let mut command = OsString::new();
+    for arg in args {
+        if !command.is_empty() { command.push(" "); }
+        command.push(quote(arg.as_ref()));
+    }
+    command
+}
+
+/// Prefix `command` with `cd <dir> && `, with `dir` [quote]d.  An empty `command` launches the user's `$SHELL` in `dir`.
+pub(crate) fn cd(dir: &OsStr, command: &OsStr) -> OsString {
+    let mut prefix = OsString::from("cd ");
+    prefix.push(quote(dir));
+    and_then(prefix, command)
+}
+
+/// Prefix `command` with `export <KEY=VALUE>... && `, with each `KEY=VALUE` [quote]d.  An empty `command` launches the
+/// user's `$SHELL` with the variables set.  Keys are assumed to have already been validated.
+///
+/// `export` is used instead of `env KEY=VALUE command`, so `$KEY` is visible to `command` itself (`env` would only set
+/// it *after* the shell had already expanded `command`), and so shell builtins and compound commands still work.
+pub(crate) fn export(vars: &[(OsString, OsString)], command: &OsStr) -> OsString {
+    if vars.is_empty() { return command.to_owned() }
+    let mut prefix = OsString::from("export");
+    for (key, value) in vars {
+        let mut kv = key.clone();
+        kv.push("=");
+        kv.push(value);
+        prefix.push(" ");
+        prefix.push(quote(&kv));
+    }
+    and_then(prefix, command)
+}
+
+/// Wrap `command` to run as `user` via `runuser` (if already root) or `sudo -n` (otherwise, failing instead of
+/// prompting for a password.)  An empty `command` launches `user`'s `$SHELL`.
+///
+/// `user` is a user name, or an all-digits UID, which is resolved to a user name via `id -nu` since `runuser` only
+/// accepts names.  `command` is run via `/bin/sh -c`, so any [export]ed variables should already be part of it.
+pub(crate) fn as_user(user: &OsStr, command: &OsStr) -> OsString {
+    let wuser = user.encode_wide().collect::<Vec<_>>();
+    let user = if !wuser.is_empty() && wuser.iter().all(|&ch| (0x30 ..= 0x39).contains(&ch)) {
+        let mut id = OsString::from("\"$(id -nu ");
+        id.push(user);
+        id.push(")\"");
+        id
+    } else {
+        quote(user)
+    };
+
+    let mut wrapped = OsString::from("if [ \"$(id -u)\" = 0 ]; then set -- runuser; else set -- sudo -n; fi && exec \"$@\" -u ");
+    wrapped.push(user);
+    wrapped.push(" -- /bin/sh -c ");
+    if command.is_empty() {
+        wrapped.push(quote(OsStr::new("exec \"${SHELL:-/bin/sh}\"")));
+    } else {
+        wrapped.push(quote(command));
+    }
+    wrapped
+}
+
+fn and_then(mut prefix: OsString, command: &OsStr) -> OsString {
+    prefix.push(" && ");
+    if command.is_empty() {
+        prefix.push("exec \"${SHELL:-/bin/sh}\"");
+    } else {
+        prefix.push(command);
+    }
+    prefix
+}
+
+fn is_safe(ch: u16) -> bool {
+    match ch {
+        0x30 ..= 0x39   => true, // 0-9
+        0x41 ..= 0x5A   => true, // A-Z
+        0x61 ..= 0x7A   => true, // a-z
+        _               => b"_-./:,+@%".iter().any(|&safe| ch == u16::from(safe)),
+    }
+}
+
+#[test] fn quoting() {
+    let join = |args: &[&str]| join(args).into_string().unwrap();
+    assert_eq!(join(&[]),                                   "");
+    assert_eq!(join(&[""]),                                 "''");
+    assert_eq!(join(&["echo", "hello world"]),              "echo 'hello world'");
+    assert_eq!(join(&["ls", "-la", "/mnt/c/Program Files"]), "ls -la '/mnt/c/Program Files'");
+    assert_eq!(join(&["echo", "it's", "$HOME", "`id`"]),    r"echo 'it'\''s' '$HOME' '`id`'");
+    assert_eq!(join(&["a=b", "--opt=1,2"]),                 "'a=b' '--opt=1,2'"); // don't let `a=b` become an assignment
+    assert_eq!(join(&["cat", "~/.bashrc"]),                 "cat '~/.bashrc'");
+}
+
+#[test] fn cd_prefix() {
+    let cd = |dir: &str, command: &str| cd(OsStr::new(dir), OsStr::new(command)).into_string().unwrap();
+    assert_eq!(cd("/tmp", "ls -la"),                "cd /tmp && ls -la");
+    assert_eq!(cd("/mnt/c/Program Files", "ls"),    "cd '/mnt/c/Program Files' && ls");
+    assert_eq!(cd("/tmp", ""),                      r#"cd /tmp && exec "${SHELL:-/bin/sh}""#);
+}
+
+#[test] fn export_prefix() {
+    let export = |vars: &[(&str, &str)], command: &str| export(&vars.iter().map(|(k, v)| (k.into(), v.into())).collect::<Vec<_>>(), OsStr::new(command)).into_string().unwrap();
+    assert_eq!(export(&[], "echo $A"),                                  "echo $A");
+    assert_eq!(export(&[("A", "1")], "echo $A"),                        "export 'A=1' && echo $A");
+    assert_eq!(export(&[("A", "it's"), ("B", "x y")], "env"),           r"export 'A=it'\''s' 'B=x y' && env");
+    assert_eq!(export(&[("A", "1")], ""),                               r#"export 'A=1' && exec "${SHELL:-/bin/sh}""#);
+}
+
+#[test] fn as_user_prefix() {
+    let as_user = |user: &str, command: &str| as_user(OsStr::new(user), OsStr::new(command)).into_string().unwrap();
+    let sudo = r#"if [ "$(id -u)" = 0 ]; then set -- runuser; else set -- sudo -n; fi && exec "$@" -u "#;
+    assert_eq!(as_user("postgres", "id -u"),    format!("{}postgres -- /bin/sh -c 'id -u'", sudo));
+    assert_eq!(as_user("it's", "echo $HOME"),   format!(r"{}'it'\''s' -- /bin/sh -c 'echo $HOME'", sudo));
+    assert_eq!(as_user("1000", ""),             format!(r#"{}"$(id -nu 1000)" -- /bin/sh -c 'exec "${{SHELL:-/bin/sh}}"'"#, sudo));
+}
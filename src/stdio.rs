@@ -1,15 +1,22 @@
+use crate::{PipeReader, PipeWriter};
+use crate::pipe::PipeFeeder;
+
+use winapi::shared::minwindef::DWORD;
 use winapi::shared::ntdef::HANDLE;
-use winapi::um::handleapi::{CloseHandle, DuplicateHandle};
+use winapi::shared::winerror::{ERROR_ACCESS_DENIED, ERROR_ALREADY_EXISTS, ERROR_FILE_EXISTS, ERROR_SHARING_VIOLATION};
+use winapi::um::handleapi::{CloseHandle, DuplicateHandle, SetHandleInformation, INVALID_HANDLE_VALUE};
+use winapi::um::processenv::GetStdHandle;
 use winapi::um::processthreadsapi::GetCurrentProcess;
-use winapi::um::winbase::FILE_FLAG_DELETE_ON_CLOSE;
+use winapi::um::winbase::{FILE_FLAG_DELETE_ON_CLOSE, HANDLE_FLAG_INHERIT, STD_ERROR_HANDLE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE};
 use winapi::um::winnt::{DUPLICATE_CLOSE_SOURCE, DUPLICATE_SAME_ACCESS, FILE_ATTRIBUTE_TEMPORARY};
 
 use std::convert::TryFrom;
 use std::fs::{File, OpenOptions};
-use std::io::{self, Seek, SeekFrom, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::os::windows::prelude::*;
-use std::os::windows::io::{AsRawHandle, RawHandle};
+use std::os::windows::io::{AsRawHandle, BorrowedHandle, OwnedHandle, RawHandle};
 use std::ops::Drop;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering::AcqRel};
 use std::ptr::null_mut;
 
@@ -28,21 +35,87 @@ impl Stdio {
     pub fn null() -> Self { Self { owner: None } }
 
     /// Stream data from a temporary file containing the contents of `bytes`
+    ///
+    /// Empty `bytes` skip the temporary file entirely, returning [Stdio::null] (which yields EOF just as quickly.)
     pub fn from_bytes(bytes: impl AsRef<[u8]>) -> io::Result<Self> {
-        let path = std::env::temp_dir().join(format!("wslapi-{}-{}.tmp", std::process::id(), COUNTER.fetch_add(1, AcqRel)));
+        if bytes.as_ref().is_empty() { return Ok(Self::null()) }
+        Self::from_file(temp_file(&std::env::temp_dir(), true, |file| file.write_all(bytes.as_ref()))?.0)
+    }
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .read(true)
-            .attributes(FILE_ATTRIBUTE_TEMPORARY)       // prefer in-memory cache
-            .custom_flags(FILE_FLAG_DELETE_ON_CLOSE)    // cleanup after use
-            .open(&path)?;
+    /// Like [from_bytes](#method.from_bytes), but the temporary file is created in `dir` instead of [std::env::temp_dir].
+    ///
+    /// Useful if `%TEMP%` is slow, encrypted, or redirected by a sandbox.  `dir` must already exist.
+    pub fn from_bytes_in(dir: impl AsRef<Path>, bytes: impl AsRef<[u8]>) -> io::Result<Self> {
+        if bytes.as_ref().is_empty() { return Ok(Self::null()) }
+        Self::from_file(temp_file(dir.as_ref(), true, |file| file.write_all(bytes.as_ref()))?.0)
+    }
+
+    /// Like [from_bytes](#method.from_bytes), but the temporary file is left on disk (for debugging) instead of
+    /// being deleted when closed.  Returns the path of the temporary file, which the caller is responsible for
+    /// cleaning up, so the exact input can be inspected or fed to the command again manually.
+    pub fn from_bytes_keep_temp(bytes: impl AsRef<[u8]>) -> io::Result<(Self, PathBuf)> {
+        let (file, path) = temp_file(&std::env::temp_dir(), false, |file| file.write_all(bytes.as_ref()))?;
+        Ok((Self::from_file(file)?, path))
+    }
 
-        file.write_all(bytes.as_ref())?;
-        file.seek(SeekFrom::Start(0))?;
+    /// Stream data from a temporary file containing the contents of `reader`.
+    ///
+    /// Unlike [from_bytes](#method.from_bytes), the data is copied into the temporary file in chunks,
+    /// without first buffering everything in memory.
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        Self::from_file(temp_file(&std::env::temp_dir(), true, |file| io::copy(&mut reader, file).map(|_| ()))?.0)
+    }
 
-        Self::from_file(file)
+    /// Stream `bytes` through an anonymous pipe, written by a background thread.
+    ///
+    /// Unlike [from_bytes](#method.from_bytes), this never touches the filesystem.  The background thread closes
+    /// the pipe (EOF) once everything has been written, and is joined when the [Stdio] is dropped.  If the child
+    /// process exits without reading everything, the remaining bytes are silently discarded.
+    pub fn pipe_bytes(bytes: impl Into<Vec<u8>>) -> io::Result<Self> {
+        Ok(Self { owner: Some(Box::new(PipeFeeder::new(bytes.into())?)) })
+    }
+
+    /// Best-effort: share the current process's **STDIN** (via [GetStdHandle]) with the WSL process.
+    ///
+    /// This works when the current process's **STDIN** is redirected from a file or pipe.  Raw console handles,
+    /// however, generally can't be used by another process - use [Library::launch_interactive] to share the console.
+    /// If the current process has no **STDIN**, this is equivalent to [Stdio::null].
+    ///
+    /// [GetStdHandle]:         https://docs.microsoft.com/en-us/windows/console/getstdhandle
+    /// [Library::launch_interactive]:  crate::Library::launch_interactive
+    pub fn inherit_stdin() -> io::Result<Self> { Self::inherit_std(STD_INPUT_HANDLE) }
+
+    /// Best-effort: share the current process's **STDOUT** (via [GetStdHandle]) with the WSL process.
+    ///
+    /// This works when the current process's **STDOUT** is redirected to a file or pipe.  Raw console handles,
+    /// however, generally can't be used by another process - use [Library::launch_interactive] to share the console.
+    /// If the current process has no **STDOUT**, this is equivalent to [Stdio::null].
+    ///
+    /// [GetStdHandle]:         https://docs.microsoft.com/en-us/windows/console/getstdhandle
+    /// [Library::launch_interactive]:  crate::Library::launch_interactive
+    pub fn inherit_stdout() -> io::Result<Self> { Self::inherit_std(STD_OUTPUT_HANDLE) }
+
+    /// Best-effort: share the current process's **STDERR** (via [GetStdHandle]) with the WSL process.
+    ///
+    /// This works when the current process's **STDERR** is redirected to a file or pipe.  Raw console handles,
+    /// however, generally can't be used by another process - use [Library::launch_interactive] to share the console.
+    /// If the current process has no **STDERR**, this is equivalent to [Stdio::null].
+    ///
+    /// [GetStdHandle]:         https://docs.microsoft.com/en-us/windows/console/getstdhandle
+    /// [Library::launch_interactive]:  crate::Library::launch_interactive
+    pub fn inherit_stderr() -> io::Result<Self> { Self::inherit_std(STD_ERROR_HANDLE) }
+
+    fn inherit_std(std_handle: DWORD) -> io::Result<Self> {
+        let original = unsafe { GetStdHandle(std_handle) };
+        if original == INVALID_HANDLE_VALUE { return Err(io::Error::last_os_error()) }
+        if original.is_null() { return Ok(Self::null()) }
+
+        // Duplicate instead of borrowing: we need an inheritable handle, and the original isn't ours to close.
+        let proc = unsafe { GetCurrentProcess() };
+        let mut handle = null_mut();
+        let success = unsafe { DuplicateHandle(proc, original, proc, &mut handle, 0, 1, DUPLICATE_SAME_ACCESS) };
+        if success == 0 { return Err(io::Error::last_os_error()) }
+        Ok(unsafe { Self::from_handle(handle) })
     }
 
     /// Stream data from or into a file handle
@@ -54,6 +127,66 @@ impl Stdio {
         Ok(unsafe { Self::from_handle(handle) })
     }
 
+    /// Stream data from or into a file handle, *without* taking ownership of `file`.
+    ///
+    /// The handle is duplicated (inheritably), so `file` remains open and usable - e.g. to pass the same file as both
+    /// **STDOUT** and **STDERR**, or to keep reading/writing it yourself after the WSL process exits.  The duplicate
+    /// shares `file`'s underlying file object, including its file position, and keeps the file open until the [Stdio]
+    /// (and the WSL process's inherited copy) are closed, even if `file` itself is dropped first.
+    pub fn from_file_ref(file: &File) -> io::Result<Self> {
+        let proc = unsafe { GetCurrentProcess() };
+        let mut handle = null_mut();
+        let success = unsafe { DuplicateHandle(proc, file.as_raw_handle().cast(), proc, &mut handle, 0, 1, DUPLICATE_SAME_ACCESS) };
+        if success == 0 { return Err(io::Error::last_os_error()) }
+        Ok(unsafe { Self::from_handle(handle) })
+    }
+
+    /// Take ownership of an existing handle - e.g. one end of a pipe you created yourself (`os_pipe`, [CreatePipe], ...)
+    /// for a broader IPC scheme - and mark it [inheritable], as [WslLaunch] requires.
+    ///
+    /// Unlike [from_handle](#method.from_handle), this is safe: [OwnedHandle] already guarantees the handle is open and
+    /// ours to close.  Marking it inheritable means any *other* processes you spawn with `bInheritHandles`=`TRUE` while
+    /// this [Stdio] is alive will inherit it too, which can delay pipe EOF - drop the [Stdio] (or the [Process]) promptly.
+    ///
+    /// [CreatePipe]:           https://docs.microsoft.com/en-us/windows/win32/api/namedpipeapi/nf-namedpipeapi-createpipe
+    /// [inheritable]:          https://docs.microsoft.com/en-us/windows/win32/sysinfo/handle-inheritance
+    /// [OwnedHandle]:          https://doc.rust-lang.org/std/os/windows/io/struct.OwnedHandle.html
+    /// [Process]:              crate::Process
+    /// [WslLaunch]:            https://docs.microsoft.com/en-us/windows/win32/api/wslapi/nf-wslapi-wsllaunch
+    pub fn from_owned_handle(handle: impl Into<OwnedHandle>) -> io::Result<Self> {
+        let handle = handle.into();
+        let success = unsafe { SetHandleInformation(handle.as_raw_handle().cast(), HANDLE_FLAG_INHERIT, HANDLE_FLAG_INHERIT) };
+        if success == 0 { return Err(io::Error::last_os_error()) }
+        Ok(Self { owner: Some(Box::new(handle)) })
+    }
+
+    /// Stream data from the existing file at `path` (e.g. for **STDIN**.)
+    pub fn open_read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_file(File::open(path)?)
+    }
+
+    /// Stream data into the file at `path` (e.g. for **STDOUT** or **STDERR**), creating or truncating it.
+    pub fn create_write(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_file(File::create(path)?)
+    }
+
+    /// Create a new, independently owned [Stdio] referring to the same underlying handle (or [Stdio::null].)
+    ///
+    /// The handle is duplicated (inheritably), so both [Stdio]s may be passed to [Library::launch] - e.g. as both
+    /// **STDOUT** and **STDERR** - and closed independently without double-closing anything.
+    ///
+    /// [Library::launch]:      crate::Library::launch
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let original = self.as_winapi_handle();
+        if original.is_null() { return Ok(Self::null()) }
+
+        let proc = unsafe { GetCurrentProcess() };
+        let mut handle = null_mut();
+        let success = unsafe { DuplicateHandle(proc, original, proc, &mut handle, 0, 1, DUPLICATE_SAME_ACCESS) };
+        if success == 0 { return Err(io::Error::last_os_error()) }
+        Ok(unsafe { Self::from_handle(handle) })
+    }
+
     /// Take ownership of a raw handle
     ///
     /// # <span style="color: red">Safety</span>
@@ -82,6 +215,7 @@ impl Stdio {
     ///
     /// * [WslLaunch]\(..., owner.as_raw_handle(), ...\) must be legal for stdIn, stdOut, or stdErr
     /// * The handle must be [inheritable] *without* [CreateProcess]'s `bInheritHandles`=`TRUE`
+    /// * `owner` must be safe to drop on another thread (`feature = "async"`'s `Process::wait_async` future is [Send])
     ///
     /// [CloseHandle]:          https://docs.microsoft.com/en-us/windows/win32/api/handleapi/nf-handleapi-closehandle
     /// [CreateProcess]:        https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createprocessw
@@ -105,6 +239,62 @@ impl Stdio {
     pub fn as_winapi_handle(&self) -> winapi::shared::ntdef::HANDLE {
         self.owner.as_ref().map_or(null_mut(), |owner| owner.as_raw_handle()).cast()
     }
+
+    /// Get an I/O-safe std::os::windows::io::[BorrowedHandle], or `None` for [Stdio::null].
+    ///
+    /// [BorrowedHandle]:       https://doc.rust-lang.org/std/os/windows/io/struct.BorrowedHandle.html
+    pub fn as_handle(&self) -> Option<BorrowedHandle<'_>> {
+        let handle = self.as_raw_handle();
+        if handle.is_null() { None } else { Some(unsafe { BorrowedHandle::borrow_raw(handle) }) }
+    }
+}
+
+/// An explicit choice of how to connect a WSL process's **STDIN**, **STDOUT**, or **STDERR**, for [Library::launch_with].
+///
+/// [Library::launch_with]:         crate::Library::launch_with
+pub enum StdioKind {
+    /// The stream will be ignored, as if attached to `/dev/null` (see [Stdio::null])
+    Null,
+
+    /// Share the current process's corresponding stream (see [Stdio::inherit_stdout] for caveats regarding consoles)
+    Inherit,
+
+    /// Connect the stream to an anonymous pipe, accessible via [Process::stdin_mut] etc.
+    ///
+    /// [Process::stdin_mut]:       crate::Process::stdin_mut
+    Piped,
+
+    /// Read from or write to a file (see [Stdio::from_file])
+    File(File),
+
+    /// Read from a temporary file containing these bytes (see [Stdio::from_bytes].)  Mostly useful for **STDIN**.
+    Bytes(Vec<u8>),
+}
+
+impl StdioKind {
+    pub(crate) fn into_input(self) -> io::Result<(Stdio, Option<PipeWriter>)> {
+        match self {
+            StdioKind::Piped    => crate::pipe::stdin_pipe().map(|(child, parent)| (child, Some(parent))),
+            other               => Ok((other.into_stdio(Stdio::inherit_stdin)?, None)),
+        }
+    }
+
+    pub(crate) fn into_output(self, inherit: fn() -> io::Result<Stdio>) -> io::Result<(Stdio, Option<PipeReader>)> {
+        match self {
+            StdioKind::Piped    => crate::pipe::output_pipe().map(|(child, parent)| (child, Some(parent))),
+            other               => Ok((other.into_stdio(inherit)?, None)),
+        }
+    }
+
+    fn into_stdio(self, inherit: fn() -> io::Result<Stdio>) -> io::Result<Stdio> {
+        match self {
+            StdioKind::Null         => Ok(Stdio::null()),
+            StdioKind::Inherit      => inherit(),
+            StdioKind::Piped        => unreachable!("StdioKind::Piped should be handled by into_input / into_output"),
+            StdioKind::File(file)   => Stdio::from_file(file),
+            StdioKind::Bytes(bytes) => Stdio::from_bytes(bytes),
+        }
+    }
 }
 
 impl AsRawHandle for Stdio {
@@ -121,12 +311,19 @@ impl AsRawHandle for Stdio {
 impl From<()>           for Stdio { fn from(_value: ())     -> Self { Self::null() } }
 
 // "Console handles can be duplicated for use only in the same process."
+// See Stdio::inherit_{stdin,stdout,stderr} for a best-effort alternative that handles redirected stdio.
 //impl From<Stderr>       for Stdio { fn from(value: Stderr)  -> Self { Self { owner: Some(Box::new(value)) } } }
 //impl From<Stdin >       for Stdio { fn from(value: Stdin )  -> Self { Self { owner: Some(Box::new(value)) } } }
 //impl From<Stdout>       for Stdio { fn from(value: Stdout)  -> Self { Self { owner: Some(Box::new(value)) } } }
 
+impl TryFrom<OwnedHandle> for Stdio { fn try_from(value: OwnedHandle) -> io::Result<Self> { Self::from_owned_handle(value) } type Error = io::Error; }
 impl TryFrom<File>      for Stdio { fn try_from(value: File) -> io::Result<Self> { Self::from_file( value) } type Error = io::Error; }
 
+/// Opens the path for writing (via [Stdio::create_write]), creating or truncating it.  Use [Stdio::open_read] for **STDIN**.
+impl TryFrom<&Path>     for Stdio { fn try_from(value: &Path)   -> io::Result<Self> { Self::create_write(value) } type Error = io::Error; }
+/// Opens the path for writing (via [Stdio::create_write]), creating or truncating it.  Use [Stdio::open_read] for **STDIN**.
+impl TryFrom<PathBuf>   for Stdio { fn try_from(value: PathBuf) -> io::Result<Self> { Self::create_write(value) } type Error = io::Error; }
+
 impl TryFrom<Vec<u8>>   for Stdio { fn try_from(value: Vec<u8>) -> io::Result<Self> { Self::from_bytes(&value) } type Error = io::Error; }
 impl TryFrom<&[u8]>     for Stdio { fn try_from(value: &[u8])   -> io::Result<Self> { Self::from_bytes(value)  } type Error = io::Error; }
 impl TryFrom<String>    for Stdio { fn try_from(value: String)  -> io::Result<Self> { Self::from_bytes(&value) } type Error = io::Error; }
@@ -134,6 +331,62 @@ impl TryFrom<&str>      for Stdio { fn try_from(value: &str)    -> io::Result<Se
 
 
 
+/// Create a temporary stdin file in `dir`, `fill` it, and rewind it for reading.  Errors mention the path of the temporary file.
+fn temp_file(dir: &Path, delete_on_close: bool, fill: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<(File, PathBuf)> {
+    // A stale file from a crashed run (possibly of a recycled pid) may still exist, or even still be locked: never reuse it.
+    let mut first_err = None;
+    let mut access_denied = 0;
+    let mut attempts = 0;
+    let (mut file, path) = loop {
+        let path = dir.join(format!("wslapi-{}-{}.tmp", std::process::id(), COUNTER.fetch_add(1, AcqRel)));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .read(true)
+            .attributes(FILE_ATTRIBUTE_TEMPORARY)       // prefer in-memory cache
+            .custom_flags(if delete_on_close { FILE_FLAG_DELETE_ON_CLOSE } else { 0 }) // cleanup after use
+            .open(&path);
+
+        let err = match file {
+            Ok(file) => break (file, path),
+            Err(err) => err,
+        };
+        attempts += 1;
+        let retry = if err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+            // Also what you get for a file that's pending deletion - but more likely, `dir` just isn't writable: only
+            // retry a couple of times, and only if something is actually in the way.
+            access_denied += 1;
+            access_denied <= 2 && exists(&path)
+        } else {
+            is_collision(&err)
+        };
+        let err = io::Error::new(err.kind(), format!("unable to create temp stdin file {}: {}", path.display(), err));
+        if !retry { return Err(if access_denied > 1 { first_err.unwrap_or(err) } else { err }) }
+        if first_err.is_none() { first_err = Some(err) }
+        if attempts >= 100 { return Err(first_err.unwrap()) }
+    };
+    let context = |what, err: io::Error| io::Error::new(err.kind(), format!("unable to {} temp stdin file {}: {}", what, path.display(), err));
+
+    fill(&mut file).map_err(|err| context("write", err))?;
+    file.seek(SeekFrom::Start(0)).map_err(|err| context("rewind", err))?;
+    Ok((file, path))
+}
+
+fn is_collision(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(code) => [ERROR_FILE_EXISTS, ERROR_ALREADY_EXISTS, ERROR_SHARING_VIOLATION].contains(&(code as u32)),
+        None => err.kind() == io::ErrorKind::AlreadyExists,
+    }
+}
+
+/// Does anything exist at `path`?  Querying a file that's pending deletion typically fails with `ERROR_ACCESS_DENIED`.
+fn exists(path: &Path) -> bool {
+    match std::fs::symlink_metadata(path) {
+        Ok(_)   => true,
+        Err(err) => err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32),
+    }
+}
+
 struct OwnHandle(HANDLE);
 
 impl AsRawHandle for OwnHandle {
@@ -147,3 +400,46 @@ impl Drop for OwnHandle {
         assert_ne!(0, succeeded, "CloseHandle(0x{:08x}) failed: {:?}", self.0 as usize, std::io::Error::last_os_error());
     }
 }
+
+#[test] fn try_clone() {
+    assert!(Stdio::null().try_clone().unwrap().as_raw_handle().is_null());
+
+    let original = Stdio::from_bytes("testing 123").unwrap();
+    let clone = original.try_clone().unwrap();
+    assert!(!clone.as_raw_handle().is_null());
+    assert_ne!(original.as_raw_handle(), clone.as_raw_handle());
+    drop(original); // OwnHandle asserts CloseHandle succeeds: both must own independent handles
+    drop(clone);
+}
+
+#[test] fn temp_file_errors() {
+    // Not a collision: should fail immediately, naming the first (and only) path tried
+    let dir = std::env::temp_dir().join("wslapi-nonexistent-dir");
+    let err = temp_file(&dir, true, |_| Ok(())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound, "{}", err);
+    assert!(err.to_string().contains("wslapi-nonexistent-dir"), "{}", err);
+    assert!(!exists(&dir));
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test] #[ignore = "writes >4 GiB to %TEMP%: run with `cargo test large_input -- --ignored`"] fn large_input() {
+    // WriteFile/ReadFile take 32-bit lengths, and 32-bit file offsets would wrap: check we're past u32::MAX intact.
+    let len = (4 << 30) + 3;
+    let mut bytes = vec![0u8; len];
+    bytes[len-1] = 42;
+    let stdio = Stdio::from_bytes(&bytes).unwrap();
+    drop(bytes);
+
+    let mut file = File::from(stdio.as_handle().unwrap().try_clone_to_owned().unwrap()); // shares the file position
+    assert_eq!(file.metadata().unwrap().len(), len as u64);
+    assert_eq!(file.stream_position().unwrap(), 0, "from_bytes should rewind to the start of the temp file");
+    let mut last = [0u8];
+    file.seek(SeekFrom::End(-1)).unwrap();
+    file.read_exact(&mut last).unwrap();
+    assert_eq!(last, [42]);
+
+    let (file, path) = temp_file(&std::env::temp_dir(), true, |file| file.write_all(&[1, 2, 3])).unwrap();
+    assert!(path.exists());
+    drop(file);
+    assert!(!path.exists(), "FILE_FLAG_DELETE_ON_CLOSE temp file {} still exists", path.display());
+}
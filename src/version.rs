@@ -0,0 +1,39 @@
+//! The version of the installed Windows Subsystem for Linux itself (as opposed to a distribution's WSL 1 vs 2)
+
+
+
+/// Get the version of the installed WSL package (for example, `"2.0.9.0"`), via `wsl --version`.
+///
+/// Returns `None` if `wsl.exe` couldn't be launched, or is an older inbox version that doesn't support `--version`
+/// (the Store/MSIX version of WSL is required), or if the output couldn't be parsed.
+/// The (possibly localized) banner is ignored: only the first dotted version number is returned.
+///
+/// # Example
+///
+/// ```rust
+/// if let Some(version) = wslapi::version::wsl_version() {
+///     assert!(version.split('.').all(|n| n.parse::<u32>().is_ok()), "{}", version);
+/// }
+/// ```
+pub fn wsl_version() -> Option<String> {
+    let output = crate::wsl_exe::run(&["--version".as_ref()]).ok()?;
+    parse_wsl_version(&output)
+}
+
+/// Find the first dotted version number (e.g. `2.0.9.0`) in the first line of `wsl --version` output.
+fn parse_wsl_version(output: &str) -> Option<String> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    line.split(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .map(|word| word.trim_matches('.'))
+        .find(|word| word.contains('.') && word.split('.').all(|n| !n.is_empty()))
+        .map(String::from)
+}
+
+#[test] fn parse() {
+    assert_eq!(parse_wsl_version("WSL version: 2.0.9.0\r\nKernel version: 5.15.133.1-1\r\n").as_deref(),  Some("2.0.9.0"));
+    assert_eq!(parse_wsl_version("WSL-Version: 2.0.9.0\r\nKernelversion: 5.15.133.1-1\r\n").as_deref(),    Some("2.0.9.0"));
+    assert_eq!(parse_wsl_version("Version de WSL\u{a0}: 1.2.5.0\r\n").as_deref(),                           Some("1.2.5.0"));
+    assert_eq!(parse_wsl_version("WSL 版本： 2.1.5.0\n").as_deref(),                                        Some("2.1.5.0"));
+    assert_eq!(parse_wsl_version("Invalid command line option: --version\n"),                               None);
+    assert_eq!(parse_wsl_version(""),                                                                       None);
+}
@@ -0,0 +1,142 @@
+//! `feature = "async"`: [Process::wait_async]
+
+use crate::{ExitStatus, Process};
+
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::ntdef::{BOOLEAN, HANDLE, PVOID};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::threadpoollegacyapiset::UnregisterWaitEx;
+use winapi::um::winbase::{INFINITE, RegisterWaitForSingleObject};
+use winapi::um::winnt::WT_EXECUTEONLYONCE;
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::ptr::null_mut;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+
+
+impl Process {
+    /// Asynchronously waits for the WSL process to exit completely.
+    ///
+    /// Rather than blocking a thread per process, the process handle is registered with the Windows thread pool via
+    /// [RegisterWaitForSingleObject], which wakes the future when the process exits.  This works with any executor.
+    ///
+    /// Like [wait](Process::wait), the **STDIN** pipe (if any) is closed first, to avoid deadlocking a process that's
+    /// waiting for EOF.  Any **STDOUT** / **STDERR** pipes are closed too, so [take](#method.take_stdout) them first if
+    /// you still want to read what was written.
+    ///
+    /// **Dropping the future before it completes [detach](Process::detach)es the process** rather than blocking the
+    /// executor until it exits: the WSL process keeps running in the background.  [kill](Process::kill) it first if
+    /// that's not what you want.  The future is [Send], so it can be spawned on multi-threaded executors.
+    ///
+    /// [RegisterWaitForSingleObject]:  https://docs.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-registerwaitforsingleobject
+    pub fn wait_async(mut self) -> impl Future<Output = io::Result<ExitStatus>> {
+        // Nothing can reach these pipes once we've consumed `self`: close them now, lest the process wait on us forever
+        self.stdin_pipe  = None;
+        self.stdout_pipe = None;
+        self.stderr_pipe = None;
+        WaitAsync { process: self, waker: Arc::new(Mutex::new(None)), wait: null_mut() }
+    }
+}
+
+struct WaitAsync {
+    process:    Process,
+    waker:      Arc<Mutex<Option<Waker>>>,
+    wait:       HANDLE, // RegisterWaitForSingleObject registration, owning a reference to `waker`
+}
+
+// SAFETY: the process and wait registration handles are kernel handles, usable and closable from any thread, and the
+// waker shared with the thread pool callback is behind an Arc<Mutex>.  The Process's stdio owners are Send, or required
+// to be by Stdio::from_as_raw_handle's safety contract.
+unsafe impl Send for WaitAsync {}
+
+impl Future for WaitAsync {
+    type Output = io::Result<ExitStatus>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut(); // WaitAsync is Unpin
+
+        // Update the waker *before* checking for exit, lest we miss a wakeup
+        *this.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        if this.wait.is_null() && !this.process.handle.is_null() {
+            let context = Arc::into_raw(Arc::clone(&this.waker));
+            let success = unsafe { RegisterWaitForSingleObject(&mut this.wait, this.process.handle, Some(on_exit), context as PVOID, INFINITE, WT_EXECUTEONLYONCE) };
+            if success == FALSE {
+                let err = io::Error::last_os_error();
+                this.wait = null_mut();
+                drop(unsafe { Arc::from_raw(context) });
+                return Poll::Ready(Err(err));
+            }
+        }
+
+        match this.process.wait_impl(0) {
+            Ok(Some(status))    => { this.unregister(); Poll::Ready(Ok(status)) },
+            Ok(None)            => Poll::Pending,
+            Err(err)            => { this.unregister(); Poll::Ready(Err(err)) },
+        }
+    }
+}
+
+impl WaitAsync {
+    fn unregister(&mut self) {
+        let wait = std::mem::replace(&mut self.wait, null_mut());
+        if wait.is_null() { return }
+
+        // INVALID_HANDLE_VALUE: block until any in-progress callback completes, so it's safe to release its reference
+        let success = unsafe { UnregisterWaitEx(wait, INVALID_HANDLE_VALUE) };
+        if success != FALSE { drop(unsafe { Arc::from_raw(Arc::as_ptr(&self.waker)) }); }
+        // else: leak the callback's reference rather than risk a use-after-free
+    }
+}
+
+impl Drop for WaitAsync {
+    fn drop(&mut self) {
+        self.unregister();
+        // Detach instead of letting Process::drop block the executor until the process exits
+        let handle = std::mem::replace(&mut self.process.handle, null_mut());
+        if !handle.is_null() { unsafe { CloseHandle(handle) }; }
+    }
+}
+
+unsafe extern "system" fn on_exit(context: PVOID, _timed_out: BOOLEAN) {
+    let waker = &*(context as *const Mutex<Option<Waker>>);
+    // Don't panic across the FFI boundary, even if the mutex was poisoned
+    let waker = match waker.lock() { Ok(mut waker) => waker.take(), Err(_) => None };
+    if let Some(waker) = waker { waker.wake(); }
+}
+
+#[test] fn send() {
+    fn assert_send<T: Send>(_: &T) {}
+    fn check(process: Process) { assert_send(&process.wait_async()); }
+    let _ = check;
+}
+
+#[test] fn piped_stdin_eof() {
+    use crate::{Library, StdioKind};
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+    use std::time::{Duration, Instant};
+
+    struct Unpark(Thread);
+    impl Wake for Unpark { fn wake(self: Arc<Self>) { self.0.unpark(); } }
+
+    let wsl = match Library::new() { Ok(wsl) => wsl, Err(_) => return }; // WSL not installed
+    let distro = match wsl.registered_distributions().next() { Some(distro) => distro, None => return };
+
+    // `cat` only exits once it sees EOF on STDIN
+    let process = wsl.launch_with(&distro, "cat", false, StdioKind::Piped, StdioKind::Null, StdioKind::Null).unwrap();
+    let mut future = Box::pin(process.wait_async());
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let status = loop {
+        match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(status) => break status.unwrap(),
+            Poll::Pending       => { assert!(Instant::now() < deadline, "`cat` never saw EOF"); thread::park_timeout(Duration::from_secs(1)); },
+        }
+    };
+    assert!(status.success(), "{}", status);
+}
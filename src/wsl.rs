@@ -0,0 +1,84 @@
+use crate::{Configuration, Library, Output, Result, Stdio, WSL_DISTRIBUTION_FLAGS};
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::ULONG;
+
+use std::convert::TryInto;
+use std::ffi::OsStr;
+use std::path::Path;
+
+
+
+/// The subset of [Library]'s functionality that can be faked, for code that wants to be testable without a real
+/// WSL install.  Implemented by [Library], and by `MockWsl` (with `feature = "mock"`.)
+///
+/// [Library::launch] and friends return a real [Process](crate::Process), which can't be faked, and are therefore
+/// not part of this trait - use [Wsl::output] or [Wsl::launch_interactive] instead.
+///
+/// ### Example
+///
+/// ```rust,no_run
+/// use wslapi::*;
+///
+/// fn uname(wsl: &impl Wsl, distro: &str) -> Result<String> {
+///     let output = wsl.output(distro, "uname -r", false, ())?;
+///     Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+/// }
+///
+/// println!("{}", uname(&Library::new().unwrap(), "Ubuntu").unwrap());
+/// ```
+pub trait Wsl {
+    /// See [Library::is_distribution_registered]
+    fn is_distribution_registered(&self, distribution_name: impl AsRef<OsStr>) -> bool;
+
+    /// See [Library::register_distribution]
+    fn register_distribution(&self, distribution_name: impl AsRef<OsStr>, tar_gz_filename: impl AsRef<Path>) -> Result<()>;
+
+    /// See [Library::unregister_distribution]
+    fn unregister_distribution(&self, distribution_name: impl AsRef<OsStr>) -> Result<()>;
+
+    /// See [Library::configure_distribution]
+    fn configure_distribution(&self, distribution_name: impl AsRef<OsStr>, default_uid: ULONG, wsl_distribution_flags: WSL_DISTRIBUTION_FLAGS) -> Result<()>;
+
+    /// See [Library::get_distribution_configuration]
+    fn get_distribution_configuration(&self, distribution_name: impl AsRef<OsStr>) -> Result<Configuration>;
+
+    /// See [Library::launch_interactive]
+    fn launch_interactive(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool) -> Result<DWORD>;
+
+    /// See [Library::output]
+    fn output<I>(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool, stdin: I) -> Result<Output> where
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>;
+}
+
+impl Wsl for Library {
+    fn is_distribution_registered(&self, distribution_name: impl AsRef<OsStr>) -> bool {
+        Library::is_distribution_registered(self, distribution_name)
+    }
+
+    fn register_distribution(&self, distribution_name: impl AsRef<OsStr>, tar_gz_filename: impl AsRef<Path>) -> Result<()> {
+        Library::register_distribution(self, distribution_name, tar_gz_filename)
+    }
+
+    fn unregister_distribution(&self, distribution_name: impl AsRef<OsStr>) -> Result<()> {
+        Library::unregister_distribution(self, distribution_name)
+    }
+
+    fn configure_distribution(&self, distribution_name: impl AsRef<OsStr>, default_uid: ULONG, wsl_distribution_flags: WSL_DISTRIBUTION_FLAGS) -> Result<()> {
+        Library::configure_distribution(self, distribution_name, default_uid, wsl_distribution_flags)
+    }
+
+    fn get_distribution_configuration(&self, distribution_name: impl AsRef<OsStr>) -> Result<Configuration> {
+        Library::get_distribution_configuration(self, distribution_name)
+    }
+
+    fn launch_interactive(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool) -> Result<DWORD> {
+        Library::launch_interactive(self, distribution_name, command, use_current_working_directory)
+    }
+
+    fn output<I>(&self, distribution_name: impl AsRef<OsStr>, command: impl AsRef<OsStr>, use_current_working_directory: bool, stdin: I) -> Result<Output> where
+        I : TryInto<Stdio>, I::Error : Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Library::output(self, distribution_name, command, use_current_working_directory, stdin)
+    }
+}
@@ -0,0 +1,50 @@
+//! Helpers for functionality only available through `wsl.exe`, not `wslapi.dll`
+
+use crate::{Error, Result};
+
+use winapi::um::winbase::CREATE_NO_WINDOW;
+use winapi::shared::winerror::E_FAIL;
+
+use std::ffi::OsStr;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+
+
+
+/// Run `wsl.exe {args}` to completion, failing if it exits with a non-zero exit code.
+///
+/// Returns `wsl.exe`'s **STDOUT** on success.
+pub(crate) fn run(args: &[&OsStr]) -> Result<String> {
+    let output = Command::new("wsl.exe")
+        .args(args)
+        .env("WSL_UTF8", "1") // newer versions of wsl.exe otherwise write UTF16
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|err| Error::new(crate::error::io2hr(&err), format!("wsl.exe {:?} failed to launch: {}", args, err)).with_source(err))?;
+
+    let stdout = decode(&output.stdout);
+    if !output.status.success() {
+        let stderr = decode(&output.stderr);
+        let message = [stdout.trim(), stderr.trim()].iter().filter(|s| !s.is_empty()).copied().collect::<Vec<_>>().join("\n");
+        return Err(Error::new(E_FAIL, format!("wsl.exe {:?} failed with {}: {}", args, output.status, message)));
+    }
+    Ok(stdout)
+}
+
+/// Decode `wsl.exe` output, which may be UTF16LE (older versions, or if `WSL_UTF8` is ignored) or UTF8.
+pub(crate) fn decode(bytes: &[u8]) -> String {
+    let utf16 = bytes.len() % 2 == 0 && bytes.len() >= 2 && bytes.chunks(2).filter(|ch| ch[1] == 0).count() * 2 >= bytes.len() / 2;
+    if utf16 {
+        let units = bytes.chunks(2).map(|ch| u16::from_le_bytes([ch[0], ch[1]])).collect::<Vec<_>>();
+        String::from_utf16_lossy(&units).trim_start_matches('\u{FEFF}').to_owned()
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+#[test] fn decode_output() {
+    assert_eq!(decode(b""), "");
+    assert_eq!(decode(b"Ubuntu\r\n"), "Ubuntu\r\n");
+    assert_eq!(decode(b"U\0b\0u\0n\0t\0u\0\r\0\n\0"), "Ubuntu\r\n");
+    assert_eq!(decode(b"\xFF\xFEU\0b\0"), "Ub");
+}